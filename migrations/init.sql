@@ -6,6 +6,8 @@ CREATE TABLE IF NOT EXISTS sessions (
     session_id TEXT PRIMARY KEY NOT NULL,
     downstream_server_url TEXT NOT NULL,
     downstream_server_status TEXT NOT NULL,
+    -- 会话级配置覆盖（JSON，可为空），如 {"timeouts": {"first_byte": 300}}
+    config TEXT,
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
     updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
 );
@@ -18,8 +20,26 @@ ON sessions(downstream_server_status);
 CREATE INDEX IF NOT EXISTS idx_created_at
 ON sessions(created_at);
 
+-- 创建 session_upstreams 表用于存储会话的多个下游副本（负载均衡）
+-- 会话没有副本记录时直接使用 sessions.downstream_server_url
+CREATE TABLE IF NOT EXISTS session_upstreams (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL REFERENCES sessions(session_id) ON DELETE CASCADE,
+    upstream_url TEXT NOT NULL,
+    weight INTEGER NOT NULL DEFAULT 1,
+    status TEXT NOT NULL DEFAULT 'active',
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (session_id, upstream_url)
+);
+
+-- 创建索引以提高按会话查询副本的性能
+CREATE INDEX IF NOT EXISTS idx_upstream_session
+ON session_upstreams(session_id);
+
 -- 显示创建成功的信息
 SELECT '✅ sessions 表创建成功' AS status;
 
 -- 显示表结构
 .schema sessions
+.schema session_upstreams
@@ -0,0 +1,178 @@
+use sqlx::{Error as SqlxError, sqlite::SqlitePool};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+use crate::{db, models::Session};
+
+/// Cached lookup result; `None` records that the session does not exist
+struct CacheEntry {
+    session: Option<Session>,
+    expires_at: Instant,
+}
+
+/// Bounded in-memory TTL cache in front of `db::get_session`
+pub struct SessionCache {
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    capacity: usize,
+    ttl: Duration,
+    negative_ttl: Duration,
+}
+
+impl SessionCache {
+    /// Create a new session cache
+    ///
+    /// A zero `capacity` or `ttl` disables caching of found sessions, and a zero
+    /// `negative_ttl` disables caching of unknown session IDs.
+    pub fn new(capacity: usize, ttl: Duration, negative_ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            capacity,
+            ttl,
+            negative_ttl,
+        }
+    }
+
+    /// Query session information by session_id, consulting the cache first
+    pub async fn get_session(
+        &self,
+        pool: &SqlitePool,
+        session_id: &str,
+    ) -> Result<Session, SqlxError> {
+        if let Some(cached) = self.lookup(session_id) {
+            debug!("Session cache hit: {}", session_id);
+            return cached.ok_or(SqlxError::RowNotFound);
+        }
+
+        debug!("Session cache miss: {}", session_id);
+        match db::get_session(pool, session_id).await {
+            Ok(session) => {
+                self.store(session_id, Some(session.clone()));
+                Ok(session)
+            }
+            Err(SqlxError::RowNotFound) => {
+                self.store(session_id, None);
+                Err(SqlxError::RowNotFound)
+            }
+            // Transient database errors are not cached
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Drop any cached entry for session_id
+    pub fn invalidate(&self, session_id: &str) {
+        if self.entries.lock().unwrap().remove(session_id).is_some() {
+            debug!("Session cache invalidated: {}", session_id);
+        }
+    }
+
+    /// Return the cached lookup result if present and not expired
+    fn lookup(&self, session_id: &str) -> Option<Option<Session>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(session_id) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.session.clone()),
+            Some(_) => {
+                entries.remove(session_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache a lookup result, evicting entries if the cache is full
+    fn store(&self, session_id: &str, session: Option<Session>) {
+        let ttl = if session.is_some() {
+            self.ttl
+        } else {
+            self.negative_ttl
+        };
+        if self.capacity == 0 || ttl.is_zero() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= self.capacity && !entries.contains_key(session_id) {
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+        if entries.len() >= self.capacity
+            && !entries.contains_key(session_id)
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone())
+        {
+            entries.remove(&oldest);
+        }
+
+        entries.insert(
+            session_id.to_string(),
+            CacheEntry {
+                session,
+                expires_at: now + ttl,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str) -> Session {
+        Session {
+            session_id: id.to_string(),
+            downstream_server_url: "http://localhost:8080".to_string(),
+            downstream_server_status: "active".to_string(),
+            config: None,
+            upstreams: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_positive_and_negative_entries() {
+        let cache = SessionCache::new(10, Duration::from_secs(60), Duration::from_secs(60));
+        cache.store("a", Some(session("a")));
+        cache.store("missing", None);
+
+        assert_eq!(cache.lookup("a").unwrap().unwrap().session_id, "a");
+        assert!(cache.lookup("missing").unwrap().is_none());
+        assert!(cache.lookup("other").is_none());
+
+        cache.invalidate("a");
+        assert!(cache.lookup("a").is_none());
+    }
+
+    #[test]
+    fn test_expiry_and_disabled() {
+        let cache = SessionCache::new(10, Duration::from_secs(60), Duration::ZERO);
+        cache.store("missing", None);
+        assert!(cache.lookup("missing").is_none());
+
+        let cache = SessionCache::new(10, Duration::from_nanos(1), Duration::ZERO);
+        cache.store("a", Some(session("a")));
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(cache.lookup("a").is_none());
+
+        let cache = SessionCache::new(0, Duration::from_secs(60), Duration::from_secs(60));
+        cache.store("a", Some(session("a")));
+        assert!(cache.lookup("a").is_none());
+    }
+
+    #[test]
+    fn test_capacity_bound() {
+        let cache = SessionCache::new(2, Duration::from_secs(60), Duration::from_secs(60));
+        for id in ["a", "b", "c"] {
+            cache.store(id, Some(session(id)));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(cache.entries.lock().unwrap().len(), 2);
+        assert!(cache.lookup("a").is_none());
+        assert!(cache.lookup("c").is_some());
+    }
+}
@@ -1,6 +1,6 @@
-use std::path::PathBuf;
+use std::{fmt, path::PathBuf};
 
-use crate::CliArgs;
+use crate::{CliArgs, proxy::balancer::DEFAULT_HASH_HEADER};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -10,8 +10,117 @@ pub struct Config {
     pub port: u16,
     /// Database file path
     pub db_path: String,
-    /// Request timeout in seconds
+    /// Time in seconds to wait for downstream response headers (0 disables)
     pub request_timeout: u64,
+    /// Downstream connect timeout in seconds (0 disables)
+    pub connect_timeout: u64,
+    /// Maximum gap in seconds between response body chunks (0 disables)
+    pub idle_timeout: u64,
+    /// Bearer token for the admin API (admin API is disabled when unset)
+    pub admin_token: Option<Secret>,
+    /// Maximum number of cached session lookups
+    pub session_cache_capacity: usize,
+    /// Session cache TTL in seconds
+    pub session_cache_ttl: u64,
+    /// Negative (unknown session) cache TTL in seconds
+    pub session_cache_negative_ttl: u64,
+    /// Maximum request body size in bytes (0 means unlimited)
+    pub max_body_size: u64,
+    /// Client WebSocket handshake headers forwarded downstream
+    pub ws_forward_headers: Vec<String>,
+    /// How the `/ws/{session_id}` prefix is mapped onto the downstream path
+    pub ws_path_mode: WsPathMode,
+    /// Replacement prefix used when `ws_path_mode` is `replace`
+    pub ws_path_prefix: String,
+    /// Maximum time in seconds to drain connections on shutdown
+    pub shutdown_timeout: u64,
+    /// PEM certificate chain for TLS termination (plain HTTP when unset)
+    pub tls_cert: Option<String>,
+    /// PEM private key for TLS termination
+    pub tls_key: Option<String>,
+    /// Number of sessions labeled individually by session_id in metrics (0 disables the label)
+    pub metrics_session_labels: usize,
+    /// How requests are spread across a session's upstreams
+    pub lb_strategy: LbStrategy,
+    /// Header hashed by the consistent-hash strategy
+    pub lb_hash_header: String,
+    /// Interval in seconds between downstream health checks (0 disables them)
+    pub health_check_interval: u64,
+    /// HTTP path probed on each downstream
+    pub health_check_path: String,
+    /// Status code a healthy downstream must return
+    pub health_check_expected_status: u16,
+    /// Health probe timeout in seconds
+    pub health_check_timeout: u64,
+    /// Consecutive successful probes before an unhealthy downstream is marked active
+    pub health_check_healthy_threshold: u32,
+    /// Consecutive failed probes before an active downstream is marked unhealthy
+    pub health_check_unhealthy_threshold: u32,
+    /// Consecutive failed requests that open a downstream's circuit breaker (0 disables)
+    pub circuit_breaker_failures: u32,
+    /// Share of 5xx/failed requests within the window that opens the breaker (0 disables)
+    pub circuit_breaker_error_rate: f64,
+    /// Number of recent requests considered for the error rate
+    pub circuit_breaker_window: usize,
+    /// Seconds a circuit stays open before a trial request
+    pub circuit_breaker_open_duration: u64,
+    /// Total attempts per proxied HTTP request, including the first (1 disables retries)
+    pub retry_max_attempts: u32,
+    /// Base retry backoff in milliseconds, doubled after each retry
+    pub retry_backoff_ms: u64,
+    /// Downstream response statuses that trigger a retry
+    pub retry_on_status: Vec<u16>,
+    /// Largest request body in bytes buffered so it can be replayed on retry
+    pub retry_max_body_size: u64,
+}
+
+/// Mapping of the `/ws/{session_id}` prefix onto the downstream WebSocket path
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WsPathMode {
+    /// Forward the full path, including `/ws/{session_id}`
+    #[default]
+    Keep,
+    /// Drop `/ws/{session_id}` and forward only the remaining path
+    Strip,
+    /// Replace `/ws/{session_id}` with `ws_path_prefix`
+    Replace,
+}
+
+/// Strategy for spreading requests across a session's upstreams
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LbStrategy {
+    /// Cycle through available upstreams in order
+    #[default]
+    RoundRobin,
+    /// Smooth weighted round-robin by upstream weight
+    Weighted,
+    /// Upstream with the fewest in-flight requests relative to its weight
+    LeastOutstanding,
+    /// Pin requests with the same hash header value to the same upstream
+    ConsistentHash,
+}
+
+/// String value that is redacted when the configuration is logged
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Get the underlying value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"***\"")
+    }
 }
 
 impl Default for Config {
@@ -21,6 +130,38 @@ impl Default for Config {
             port: 8080,
             db_path: "./sessions.db".to_string(),
             request_timeout: 30,
+            connect_timeout: 10,
+            idle_timeout: 60,
+            admin_token: None,
+            session_cache_capacity: 10_000,
+            session_cache_ttl: 5,
+            session_cache_negative_ttl: 1,
+            max_body_size: 100 * 1024 * 1024,
+            ws_forward_headers: ["authorization", "cookie", "user-agent", "x-*"]
+                .map(String::from)
+                .to_vec(),
+            ws_path_mode: WsPathMode::Keep,
+            ws_path_prefix: String::new(),
+            shutdown_timeout: 30,
+            tls_cert: None,
+            tls_key: None,
+            metrics_session_labels: 0,
+            lb_strategy: LbStrategy::RoundRobin,
+            lb_hash_header: DEFAULT_HASH_HEADER.to_string(),
+            health_check_interval: 0,
+            health_check_path: "/health".to_string(),
+            health_check_expected_status: 200,
+            health_check_timeout: 5,
+            health_check_healthy_threshold: 2,
+            health_check_unhealthy_threshold: 3,
+            circuit_breaker_failures: 5,
+            circuit_breaker_error_rate: 0.5,
+            circuit_breaker_window: 20,
+            circuit_breaker_open_duration: 30,
+            retry_max_attempts: 1,
+            retry_backoff_ms: 100,
+            retry_on_status: vec![502, 503, 504],
+            retry_max_body_size: 1024 * 1024,
         }
     }
 }
@@ -50,12 +191,147 @@ impl Config {
         self
     }
 
-    /// Set request timeout
+    /// Set time to wait for downstream response headers
     pub fn with_timeout(mut self, timeout: u64) -> Self {
         self.request_timeout = timeout;
         self
     }
 
+    /// Set downstream connect timeout
+    pub fn with_connect_timeout(mut self, timeout: u64) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set maximum gap between response body chunks
+    pub fn with_idle_timeout(mut self, timeout: u64) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Set admin API bearer token
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(Secret(token.into()));
+        self
+    }
+
+    /// Set session cache capacity and TTLs (in seconds)
+    pub fn with_session_cache(mut self, capacity: usize, ttl: u64, negative_ttl: u64) -> Self {
+        self.session_cache_capacity = capacity;
+        self.session_cache_ttl = ttl;
+        self.session_cache_negative_ttl = negative_ttl;
+        self
+    }
+
+    /// Set maximum request body size in bytes (0 means unlimited)
+    pub fn with_max_body_size(mut self, max_body_size: u64) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Set client WebSocket handshake headers forwarded downstream
+    pub fn with_ws_forward_headers<I, S>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.ws_forward_headers = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set WebSocket path mapping mode and replacement prefix
+    pub fn with_ws_path_mode(mut self, mode: WsPathMode, prefix: impl Into<String>) -> Self {
+        self.ws_path_mode = mode;
+        self.ws_path_prefix = prefix.into();
+        self
+    }
+
+    /// Set maximum time in seconds to drain connections on shutdown
+    pub fn with_shutdown_timeout(mut self, timeout: u64) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Set PEM certificate chain and private key paths for TLS termination
+    pub fn with_tls(mut self, cert: String, key: String) -> Self {
+        self.tls_cert = Some(cert);
+        self.tls_key = Some(key);
+        self
+    }
+
+    /// Set number of sessions labeled individually by session_id in metrics
+    pub fn with_metrics_session_labels(mut self, limit: usize) -> Self {
+        self.metrics_session_labels = limit;
+        self
+    }
+
+    /// Set load balancing strategy and consistent-hash header
+    pub fn with_lb_strategy(mut self, strategy: LbStrategy, hash_header: String) -> Self {
+        self.lb_strategy = strategy;
+        self.lb_hash_header = hash_header;
+        self
+    }
+
+    /// Enable downstream health checks every `interval` seconds, probing `path`
+    pub fn with_health_check(mut self, interval: u64, path: String) -> Self {
+        self.health_check_interval = interval;
+        self.health_check_path = path;
+        self
+    }
+
+    /// Set the status code a healthy downstream must return
+    pub fn with_health_check_expected_status(mut self, status: u16) -> Self {
+        self.health_check_expected_status = status;
+        self
+    }
+
+    /// Set health probe timeout in seconds
+    pub fn with_health_check_timeout(mut self, timeout: u64) -> Self {
+        self.health_check_timeout = timeout;
+        self
+    }
+
+    /// Set consecutive probe results needed before flipping a downstream's status
+    pub fn with_health_check_thresholds(mut self, healthy: u32, unhealthy: u32) -> Self {
+        self.health_check_healthy_threshold = healthy;
+        self.health_check_unhealthy_threshold = unhealthy;
+        self
+    }
+
+    /// Set circuit breaker thresholds and open duration in seconds
+    pub fn with_circuit_breaker(
+        mut self,
+        failures: u32,
+        error_rate: f64,
+        window: usize,
+        open_duration: u64,
+    ) -> Self {
+        self.circuit_breaker_failures = failures;
+        self.circuit_breaker_error_rate = error_rate;
+        self.circuit_breaker_window = window;
+        self.circuit_breaker_open_duration = open_duration;
+        self
+    }
+
+    /// Set maximum attempts per request and base retry backoff in milliseconds
+    pub fn with_retries(mut self, max_attempts: u32, backoff_ms: u64) -> Self {
+        self.retry_max_attempts = max_attempts;
+        self.retry_backoff_ms = backoff_ms;
+        self
+    }
+
+    /// Set downstream response statuses that trigger a retry
+    pub fn with_retry_on_status(mut self, statuses: Vec<u16>) -> Self {
+        self.retry_on_status = statuses;
+        self
+    }
+
+    /// Set largest request body in bytes buffered for retries
+    pub fn with_retry_max_body_size(mut self, size: u64) -> Self {
+        self.retry_max_body_size = size;
+        self
+    }
+
     /// Get database connection string
     /// Automatically handles relative and absolute paths
     pub fn database_url(&self) -> String {
@@ -83,6 +359,36 @@ impl From<CliArgs> for Config {
             port: args.port,
             db_path: args.db_path,
             request_timeout: args.timeout,
+            connect_timeout: args.connect_timeout,
+            idle_timeout: args.idle_timeout,
+            admin_token: args.admin_token.filter(|t| !t.is_empty()).map(Secret),
+            session_cache_capacity: args.session_cache_capacity,
+            session_cache_ttl: args.session_cache_ttl,
+            session_cache_negative_ttl: args.session_cache_negative_ttl,
+            max_body_size: args.max_body_size,
+            ws_forward_headers: args.ws_forward_headers,
+            ws_path_mode: args.ws_path_mode,
+            ws_path_prefix: args.ws_path_prefix,
+            shutdown_timeout: args.shutdown_timeout,
+            tls_cert: args.tls_cert,
+            tls_key: args.tls_key,
+            metrics_session_labels: args.metrics_session_labels,
+            lb_strategy: args.lb_strategy,
+            lb_hash_header: args.lb_hash_header,
+            health_check_interval: args.health_check_interval,
+            health_check_path: args.health_check_path,
+            health_check_expected_status: args.health_check_expected_status,
+            health_check_timeout: args.health_check_timeout,
+            health_check_healthy_threshold: args.health_check_healthy_threshold,
+            health_check_unhealthy_threshold: args.health_check_unhealthy_threshold,
+            circuit_breaker_failures: args.circuit_breaker_failures,
+            circuit_breaker_error_rate: args.circuit_breaker_error_rate,
+            circuit_breaker_window: args.circuit_breaker_window,
+            circuit_breaker_open_duration: args.circuit_breaker_open_duration,
+            retry_max_attempts: args.retry_max_attempts,
+            retry_backoff_ms: args.retry_backoff_ms,
+            retry_on_status: args.retry_on_status,
+            retry_max_body_size: args.retry_max_body_size,
         }
     }
 }
@@ -108,4 +414,11 @@ mod tests {
         let config = Config::new();
         assert_eq!(config.bind_address(), "0.0.0.0:8080");
     }
+
+    #[test]
+    fn test_admin_token_redacted() {
+        let config = Config::new().with_admin_token("s3cret");
+        assert_eq!(config.admin_token.as_ref().unwrap().expose(), "s3cret");
+        assert!(!format!("{:?}", config).contains("s3cret"));
+    }
 }
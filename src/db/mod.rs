@@ -1,7 +1,7 @@
-use sqlx::{Error as SqlxError, sqlite::SqlitePool};
+use sqlx::{Error as SqlxError, sqlite::SqlitePool, types::Json};
 use tracing::info;
 
-use crate::models::Session;
+use crate::models::{Session, SessionConfig, Upstream};
 
 /// Create database connection pool
 pub async fn create_pool(database_url: &str) -> Result<SqlitePool, SqlxError> {
@@ -13,61 +13,386 @@ pub async fn create_pool(database_url: &str) -> Result<SqlitePool, SqlxError> {
     Ok(pool)
 }
 
-/// Query session information by session_id
+/// Query session information by session_id, including its upstream pool
 pub async fn get_session(pool: &SqlitePool, session_id: &str) -> Result<Session, SqlxError> {
-    sqlx::query_as::<_, Session>(
+    let mut session = sqlx::query_as::<_, Session>(
         r#"
-        SELECT session_id, downstream_server_url, downstream_server_status
+        SELECT session_id, downstream_server_url, downstream_server_status, config
         FROM sessions
         WHERE session_id = ?
         "#,
     )
     .bind(session_id)
     .fetch_one(pool)
+    .await?;
+
+    session.upstreams = list_upstreams(pool, session_id).await?;
+    Ok(session)
+}
+
+/// List the upstream replicas of a session
+pub async fn list_upstreams(
+    pool: &SqlitePool,
+    session_id: &str,
+) -> Result<Vec<Upstream>, SqlxError> {
+    sqlx::query_as::<_, Upstream>(
+        r#"
+        SELECT id, upstream_url, weight, status
+        FROM session_upstreams
+        WHERE session_id = ?
+        ORDER BY id
+        "#,
+    )
+    .bind(session_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// List sessions ordered by creation time, optionally filtered by status
+pub async fn list_sessions(
+    pool: &SqlitePool,
+    status: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Session>, SqlxError> {
+    sqlx::query_as::<_, Session>(
+        r#"
+        SELECT session_id, downstream_server_url, downstream_server_status, config
+        FROM sessions
+        WHERE ?1 IS NULL OR downstream_server_status = ?1
+        ORDER BY created_at, session_id
+        LIMIT ?2 OFFSET ?3
+        "#,
+    )
+    .bind(status)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+/// Count sessions, optionally filtered by status
+pub async fn count_sessions(pool: &SqlitePool, status: Option<&str>) -> Result<i64, SqlxError> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*)
+        FROM sessions
+        WHERE ?1 IS NULL OR downstream_server_status = ?1
+        "#,
+    )
+    .bind(status)
+    .fetch_one(pool)
     .await
 }
 
-/// Insert new session (for testing)
-#[allow(dead_code)]
+/// Insert new session
 pub async fn insert_session(
     pool: &SqlitePool,
     session_id: &str,
     url: &str,
     status: &str,
+    config: Option<&SessionConfig>,
 ) -> Result<(), SqlxError> {
     sqlx::query(
         r#"
-        INSERT INTO sessions (session_id, downstream_server_url, downstream_server_status)
-        VALUES (?, ?, ?)
+        INSERT INTO sessions (session_id, downstream_server_url, downstream_server_status, config)
+        VALUES (?, ?, ?, ?)
         "#,
     )
     .bind(session_id)
     .bind(url)
     .bind(status)
+    .bind(config.map(Json))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Update session URL and/or status, leaving `None` fields unchanged
+///
+/// Returns `RowNotFound` if the session does not exist.
+pub async fn update_session(
+    pool: &SqlitePool,
+    session_id: &str,
+    url: Option<&str>,
+    status: Option<&str>,
+) -> Result<(), SqlxError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions
+        SET downstream_server_url = COALESCE(?, downstream_server_url),
+            downstream_server_status = COALESCE(?, downstream_server_status),
+            updated_at = CURRENT_TIMESTAMP
+        WHERE session_id = ?
+        "#,
+    )
+    .bind(url)
+    .bind(status)
+    .bind(session_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(SqlxError::RowNotFound);
+    }
+
+    Ok(())
+}
+
+/// Replace the per-session configuration (`None` clears it)
+///
+/// Returns `RowNotFound` if the session does not exist.
+pub async fn update_session_config(
+    pool: &SqlitePool,
+    session_id: &str,
+    config: Option<&SessionConfig>,
+) -> Result<(), SqlxError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions
+        SET config = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE session_id = ?
+        "#,
+    )
+    .bind(config.map(Json))
+    .bind(session_id)
     .execute(pool)
     .await?;
 
+    if result.rows_affected() == 0 {
+        return Err(SqlxError::RowNotFound);
+    }
+
     Ok(())
 }
 
 /// Update session status
-#[allow(dead_code)]
+///
+/// Returns `RowNotFound` if the session does not exist.
 pub async fn update_session_status(
     pool: &SqlitePool,
     session_id: &str,
     status: &str,
 ) -> Result<(), SqlxError> {
-    sqlx::query(
+    update_session(pool, session_id, None, Some(status)).await
+}
+
+/// Update the status of an upstream replica
+///
+/// Returns `RowNotFound` if the upstream does not exist.
+pub async fn update_upstream_status(
+    pool: &SqlitePool,
+    upstream_id: i64,
+    status: &str,
+) -> Result<(), SqlxError> {
+    let result = sqlx::query(
         r#"
-        UPDATE sessions
-        SET downstream_server_status = ?, updated_at = CURRENT_TIMESTAMP
-        WHERE session_id = ?
+        UPDATE session_upstreams
+        SET status = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
         "#,
     )
     .bind(status)
+    .bind(upstream_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(SqlxError::RowNotFound);
+    }
+
+    Ok(())
+}
+
+/// Delete session
+///
+/// Returns `RowNotFound` if the session does not exist.
+pub async fn delete_session(pool: &SqlitePool, session_id: &str) -> Result<(), SqlxError> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM sessions
+        WHERE session_id = ?
+        "#,
+    )
     .bind(session_id)
     .execute(pool)
     .await?;
 
+    if result.rows_affected() == 0 {
+        return Err(SqlxError::RowNotFound);
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"
+            CREATE TABLE sessions (
+                session_id TEXT PRIMARY KEY NOT NULL,
+                downstream_server_url TEXT NOT NULL,
+                downstream_server_status TEXT NOT NULL,
+                config TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"
+            CREATE TABLE session_upstreams (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL REFERENCES sessions(session_id) ON DELETE CASCADE,
+                upstream_url TEXT NOT NULL,
+                weight INTEGER NOT NULL DEFAULT 1,
+                status TEXT NOT NULL DEFAULT 'active',
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (session_id, upstream_url)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_session_crud() {
+        let pool = test_pool().await;
+
+        insert_session(&pool, "a", "http://a:8080", "active", None)
+            .await
+            .unwrap();
+        insert_session(&pool, "b", "http://b:8080", "inactive", None)
+            .await
+            .unwrap();
+        assert!(
+            insert_session(&pool, "a", "http://a:8080", "active", None)
+                .await
+                .is_err()
+        );
+
+        update_session(&pool, "a", Some("http://a2:8080"), None)
+            .await
+            .unwrap();
+        let session = get_session(&pool, "a").await.unwrap();
+        assert_eq!(session.downstream_server_url, "http://a2:8080");
+        assert_eq!(session.downstream_server_status, "active");
+
+        update_session_status(&pool, "b", "active").await.unwrap();
+        assert!(get_session(&pool, "b").await.unwrap().is_available());
+
+        delete_session(&pool, "a").await.unwrap();
+        assert!(matches!(
+            get_session(&pool, "a").await,
+            Err(SqlxError::RowNotFound)
+        ));
+        assert!(matches!(
+            delete_session(&pool, "a").await,
+            Err(SqlxError::RowNotFound)
+        ));
+        assert!(matches!(
+            update_session_status(&pool, "missing", "active").await,
+            Err(SqlxError::RowNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_session_config() {
+        let pool = test_pool().await;
+        let config: SessionConfig =
+            serde_json::from_str(r#"{"max_body_size": 10, "headers": {"x-tenant": "a"}}"#).unwrap();
+        insert_session(&pool, "a", "http://a:8080", "active", Some(&config))
+            .await
+            .unwrap();
+        let session = get_session(&pool, "a").await.unwrap();
+        assert_eq!(session.config().max_body_size, Some(10));
+        assert_eq!(session.config().headers["x-tenant"], "a");
+
+        update_session_config(&pool, "a", None).await.unwrap();
+        let session = get_session(&pool, "a").await.unwrap();
+        assert!(session.config.is_none());
+        assert_eq!(session.config().max_body_size, None);
+        assert!(matches!(
+            update_session_config(&pool, "missing", None).await,
+            Err(SqlxError::RowNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_session_upstreams() {
+        let pool = test_pool().await;
+        insert_session(&pool, "a", "http://a:8080", "active", None)
+            .await
+            .unwrap();
+        assert!(get_session(&pool, "a").await.unwrap().upstreams.is_empty());
+
+        sqlx::query(
+            r#"
+            INSERT INTO session_upstreams (session_id, upstream_url, weight, status)
+            VALUES ('a', 'http://a1:8080', 1, 'active'), ('a', 'http://a2:8080', 3, 'inactive')
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let session = get_session(&pool, "a").await.unwrap();
+        let urls: Vec<_> = session
+            .upstreams
+            .iter()
+            .map(|u| u.upstream_url.as_str())
+            .collect();
+        assert_eq!(urls, ["http://a1:8080", "http://a2:8080"]);
+        assert_eq!(session.upstreams[1].weight, 3);
+        assert!(!session.upstreams[1].is_available());
+
+        update_upstream_status(&pool, session.upstreams[1].id, "active")
+            .await
+            .unwrap();
+        assert!(list_upstreams(&pool, "a").await.unwrap()[1].is_available());
+        assert!(matches!(
+            update_upstream_status(&pool, 999, "active").await,
+            Err(SqlxError::RowNotFound)
+        ));
+
+        delete_session(&pool, "a").await.unwrap();
+        assert!(list_upstreams(&pool, "a").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions() {
+        let pool = test_pool().await;
+        for (id, status) in [("a", "active"), ("b", "inactive"), ("c", "active")] {
+            insert_session(&pool, id, "http://localhost", status, None)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(count_sessions(&pool, None).await.unwrap(), 3);
+        assert_eq!(count_sessions(&pool, Some("active")).await.unwrap(), 2);
+
+        let page = list_sessions(&pool, None, 2, 1).await.unwrap();
+        let ids: Vec<_> = page.iter().map(|s| s.session_id.as_str()).collect();
+        assert_eq!(ids, ["b", "c"]);
+
+        let active = list_sessions(&pool, Some("active"), 10, 0).await.unwrap();
+        assert_eq!(active.len(), 2);
+    }
+}
@@ -0,0 +1,379 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, Request, State},
+    http::{HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::Error as SqlxError;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::{
+    db,
+    handlers::AppState,
+    models::{Session, SessionConfig},
+};
+
+/// Default page size for session listing
+const DEFAULT_PAGE_LIMIT: i64 = 50;
+/// Maximum page size for session listing
+const MAX_PAGE_LIMIT: i64 = 500;
+/// First path segments claimed by the proxy's own routes
+const RESERVED_SESSION_IDS: &[&str] = &["admin", "ws", "health", "metrics"];
+
+/// Build the admin API router, protected by a bearer token
+pub fn admin_router(state: Arc<AppState>, token: String) -> Router {
+    Router::new()
+        .route("/admin/sessions", get(list_sessions).post(create_session))
+        .route(
+            "/admin/sessions/{session_id}",
+            get(get_session)
+                .put(replace_session)
+                .patch(patch_session)
+                .delete(delete_session),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            Arc::new(token),
+            require_admin_token,
+        ))
+        .with_state(state)
+}
+
+/// Reject requests without a matching `Authorization: Bearer <token>` header
+async fn require_admin_token(
+    State(token): State<Arc<String>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match provided {
+        Some(provided) if constant_time_eq(provided.as_bytes(), token.as_bytes()) => {
+            Ok(next.run(req).await)
+        }
+        _ => {
+            warn!("Rejected unauthorized admin request: {}", req.uri());
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+/// Compare two byte strings without short-circuiting on the first mismatch
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Query parameters for session listing
+#[derive(Debug, Deserialize)]
+pub struct ListParams {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Paginated session list response
+#[derive(Debug, Serialize)]
+pub struct SessionList {
+    pub sessions: Vec<Session>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Request body for creating a session
+#[derive(Debug, Deserialize)]
+pub struct CreateSession {
+    pub session_id: String,
+    pub downstream_server_url: String,
+    pub downstream_server_status: String,
+    pub config: Option<SessionConfig>,
+}
+
+/// Request body for replacing a session; an omitted `config` clears it
+#[derive(Debug, Deserialize)]
+pub struct ReplaceSession {
+    pub downstream_server_url: String,
+    pub downstream_server_status: String,
+    pub config: Option<SessionConfig>,
+}
+
+/// Request body for partially updating a session
+#[derive(Debug, Deserialize)]
+pub struct PatchSession {
+    pub downstream_server_url: Option<String>,
+    pub downstream_server_status: Option<String>,
+    pub config: Option<SessionConfig>,
+}
+
+/// GET /admin/sessions
+async fn list_sessions(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ListParams>,
+) -> Result<Json<SessionList>, StatusCode> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let status = params.status.as_deref();
+
+    let sessions = db::list_sessions(&state.pool, status, limit, offset)
+        .await
+        .map_err(db_error)?;
+    let total = db::count_sessions(&state.pool, status)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(SessionList {
+        sessions,
+        total,
+        limit,
+        offset,
+    }))
+}
+
+/// POST /admin/sessions
+async fn create_session(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<CreateSession>,
+) -> Result<Response, StatusCode> {
+    validate_session_id(&body.session_id)?;
+    validate_url(&body.downstream_server_url)?;
+    validate_status(&body.downstream_server_status)?;
+    if let Some(config) = &body.config {
+        validate_config(config)?;
+    }
+
+    db::insert_session(
+        &state.pool,
+        &body.session_id,
+        &body.downstream_server_url,
+        &body.downstream_server_status,
+        body.config.as_ref(),
+    )
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(db_err) if db_err.is_unique_violation() => {
+            warn!("Session already exists: {}", body.session_id);
+            StatusCode::CONFLICT
+        }
+        _ => db_error(e),
+    })?;
+    state.session_cache.invalidate(&body.session_id);
+
+    info!(
+        "Admin created session: {} -> {}",
+        body.session_id, body.downstream_server_url
+    );
+
+    let session = fetch_session(&state, &body.session_id).await?;
+    Ok((StatusCode::CREATED, Json(session)).into_response())
+}
+
+/// GET /admin/sessions/{session_id}
+async fn get_session(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+) -> Result<Json<Session>, StatusCode> {
+    fetch_session(&state, &session_id).await.map(Json)
+}
+
+/// PUT /admin/sessions/{session_id}
+async fn replace_session(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Json(body): Json<ReplaceSession>,
+) -> Result<Json<Session>, StatusCode> {
+    validate_url(&body.downstream_server_url)?;
+    validate_status(&body.downstream_server_status)?;
+    if let Some(config) = &body.config {
+        validate_config(config)?;
+    }
+
+    db::update_session(
+        &state.pool,
+        &session_id,
+        Some(&body.downstream_server_url),
+        Some(&body.downstream_server_status),
+    )
+    .await
+    .map_err(db_error)?;
+    db::update_session_config(&state.pool, &session_id, body.config.as_ref())
+        .await
+        .map_err(db_error)?;
+    state.session_cache.invalidate(&session_id);
+
+    info!("Admin replaced session: {}", session_id);
+    fetch_session(&state, &session_id).await.map(Json)
+}
+
+/// PATCH /admin/sessions/{session_id}
+async fn patch_session(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Json(body): Json<PatchSession>,
+) -> Result<Json<Session>, StatusCode> {
+    if let Some(url) = &body.downstream_server_url {
+        validate_url(url)?;
+    }
+    if let Some(status) = &body.downstream_server_status {
+        validate_status(status)?;
+    }
+    if let Some(config) = &body.config {
+        validate_config(config)?;
+    }
+
+    db::update_session(
+        &state.pool,
+        &session_id,
+        body.downstream_server_url.as_deref(),
+        body.downstream_server_status.as_deref(),
+    )
+    .await
+    .map_err(db_error)?;
+    if let Some(config) = &body.config {
+        db::update_session_config(&state.pool, &session_id, Some(config))
+            .await
+            .map_err(db_error)?;
+    }
+    state.session_cache.invalidate(&session_id);
+
+    info!("Admin updated session: {}", session_id);
+    fetch_session(&state, &session_id).await.map(Json)
+}
+
+/// DELETE /admin/sessions/{session_id}
+async fn delete_session(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    db::delete_session(&state.pool, &session_id)
+        .await
+        .map_err(db_error)?;
+    state.session_cache.invalidate(&session_id);
+
+    info!("Admin deleted session: {}", session_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Load a session, mapping a missing row to 404
+async fn fetch_session(state: &AppState, session_id: &str) -> Result<Session, StatusCode> {
+    db::get_session(&state.pool, session_id)
+        .await
+        .map_err(db_error)
+}
+
+/// Map database errors to HTTP status codes
+fn db_error(e: SqlxError) -> StatusCode {
+    match e {
+        SqlxError::RowNotFound => StatusCode::NOT_FOUND,
+        e => {
+            error!("Admin database operation failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Session IDs are used as a single path segment, so they must be non-empty, slash-free
+/// and must not collide with the proxy's own routes
+fn validate_session_id(session_id: &str) -> Result<(), StatusCode> {
+    if session_id.is_empty()
+        || session_id.contains('/')
+        || RESERVED_SESSION_IDS.contains(&session_id)
+    {
+        warn!("Invalid session_id: {:?}", session_id);
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    Ok(())
+}
+
+/// Downstream URLs must be absolute http(s) or ws(s) URLs
+fn validate_url(url: &str) -> Result<(), StatusCode> {
+    match reqwest::Url::parse(url) {
+        Ok(u) if matches!(u.scheme(), "http" | "https" | "ws" | "wss") && u.has_host() => Ok(()),
+        _ => {
+            warn!("Invalid downstream_server_url: {:?}", url);
+            Err(StatusCode::UNPROCESSABLE_ENTITY)
+        }
+    }
+}
+
+/// Status must be non-empty
+fn validate_status(status: &str) -> Result<(), StatusCode> {
+    if status.trim().is_empty() {
+        warn!("Invalid downstream_server_status: {:?}", status);
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    Ok(())
+}
+
+/// Session config headers and methods must be valid HTTP tokens, and rate
+/// limits must be positive
+fn validate_config(config: &SessionConfig) -> Result<(), StatusCode> {
+    let headers_valid = config.headers.iter().all(|(name, value)| {
+        HeaderName::from_bytes(name.as_bytes()).is_ok() && HeaderValue::from_str(value).is_ok()
+    });
+    let methods_valid = config
+        .allowed_methods
+        .iter()
+        .flatten()
+        .all(|method| Method::from_bytes(method.as_bytes()).is_ok());
+    let rate_limit_valid = config
+        .rate_limit
+        .is_none_or(|limit| limit.requests_per_second > 0.0);
+
+    if !(headers_valid && methods_valid && rate_limit_valid) {
+        warn!("Invalid session config: {:?}", config);
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(validate_session_id("session-1").is_ok());
+        assert!(validate_session_id("").is_err());
+        assert!(validate_session_id("a/b").is_err());
+        assert!(validate_session_id("admin").is_err());
+        assert!(validate_session_id("ws").is_err());
+
+        assert!(validate_url("http://localhost:8080").is_ok());
+        assert!(validate_url("wss://example.com/ws").is_ok());
+        assert!(validate_url("localhost:8080").is_err());
+        assert!(validate_url("ftp://example.com").is_err());
+
+        assert!(validate_status("active").is_ok());
+        assert!(validate_status(" ").is_err());
+
+        let config = |json: &str| serde_json::from_str::<SessionConfig>(json).unwrap();
+        assert!(validate_config(&config("{}")).is_ok());
+        assert!(
+            validate_config(&config(
+                r#"{"headers": {"x-tenant": "a"}, "allowed_methods": ["GET"],
+                    "rate_limit": {"requests_per_second": 1}}"#
+            ))
+            .is_ok()
+        );
+        assert!(validate_config(&config(r#"{"headers": {"bad header": "a"}}"#)).is_err());
+        assert!(validate_config(&config(r#"{"allowed_methods": ["GE T"]}"#)).is_err());
+        assert!(validate_config(&config(r#"{"rate_limit": {"requests_per_second": 0}}"#)).is_err());
+    }
+}
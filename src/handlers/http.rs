@@ -1,14 +1,19 @@
 use axum::{
-    body::Bytes,
+    body::Body,
     extract::{Path, RawQuery, State},
-    http::{Method, StatusCode},
+    http::{Method, StatusCode, header},
     response::{IntoResponse, Response},
 };
+use futures_util::StreamExt;
 use sqlx::SqlitePool;
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 use tracing::{error, warn};
 
-use crate::{db, proxy::HttpProxy};
+use crate::{
+    cache::SessionCache,
+    metrics::{Metrics, SessionLabels},
+    proxy::{Balancer, HttpProxy, RateLimiter, WsProxy, http_proxy::ProxyError},
+};
 
 /// HTTP/HTTPS proxy handler
 pub async fn http_proxy_handler(
@@ -17,16 +22,31 @@ pub async fn http_proxy_handler(
     RawQuery(query): RawQuery,
     method: Method,
     headers: axum::http::HeaderMap,
-    body: Bytes,
+    body: Body,
 ) -> Result<Response, StatusCode> {
+    let started = Instant::now();
+    // Record requests rejected before a downstream response was received
+    let reject = |labels: &SessionLabels, status: StatusCode| {
+        state.metrics.record_http_request(labels, status);
+        state
+            .metrics
+            .observe_http_duration(labels, started.elapsed());
+        status
+    };
+
     // 1. Query database to get session information
-    let session = match db::get_session(&state.pool, &session_id).await {
+    let session = match state
+        .session_cache
+        .get_session(&state.pool, &session_id)
+        .await
+    {
         Ok(s) => s,
         Err(e) => {
             warn!("Session not found: {} - {}", session_id, e);
-            return Err(StatusCode::NOT_FOUND);
+            return Err(reject(&SessionLabels::unknown(), StatusCode::NOT_FOUND));
         }
     };
+    let labels = state.metrics.session_labels(&session);
 
     // 2. Check downstream server status
     if !session.is_available() {
@@ -34,10 +54,29 @@ pub async fn http_proxy_handler(
             "Downstream server unavailable: {} (status: {})",
             session_id, session.downstream_server_status
         );
-        return Err(StatusCode::SERVICE_UNAVAILABLE);
+        return Err(reject(&labels, StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    // 3. Apply per-session method and rate limits
+    let config = session.config();
+    if !config.allows_method(method.as_str()) {
+        warn!("Method {} not allowed for session: {}", method, session_id);
+        return Err(reject(&labels, StatusCode::METHOD_NOT_ALLOWED));
     }
+    if let Some(limit) = &config.rate_limit
+        && !state.rate_limiter.check(&session_id, limit)
+    {
+        warn!("Rate limit exceeded for session: {}", session_id);
+        return Err(reject(&labels, StatusCode::TOO_MANY_REQUESTS));
+    }
+
+    // 4. Pick a downstream from the session's upstream pool
+    let Some(upstream) = state.balancer.select(&session, &headers) else {
+        error!("No available upstream for session: {}", session_id);
+        return Err(reject(&labels, StatusCode::SERVICE_UNAVAILABLE));
+    };
 
-    // 3. Construct full path with query string
+    // 5. Construct full path with query string
     let full_path = if path.is_empty() {
         "/".to_string()
     } else if path.starts_with('/') {
@@ -53,22 +92,38 @@ pub async fn http_proxy_handler(
         full_path
     };
 
-    // 4. Forward request
+    // 6. Forward request
     match state
         .http_proxy
         .forward_request(
-            &session.downstream_server_url,
+            &upstream.url,
             &full_path_with_query,
             method,
             headers,
             body,
+            labels.clone(),
+            config,
         )
         .await
     {
-        Ok(response) => Ok(response),
+        Ok(response) => {
+            state
+                .metrics
+                .record_http_request(&labels, response.status());
+            // Keep the upstream counted as outstanding until the body is fully sent
+            Ok(response.map(|body| {
+                Body::from_stream(body.into_data_stream().map(move |chunk| {
+                    let _ = &upstream;
+                    chunk
+                }))
+            }))
+        }
+        Err(ProxyError::BodyTooLarge(_)) => Err(reject(&labels, StatusCode::PAYLOAD_TOO_LARGE)),
+        Err(ProxyError::CircuitOpen(_)) => Err(reject(&labels, StatusCode::SERVICE_UNAVAILABLE)),
+        Err(ProxyError::Timeout(_)) => Err(reject(&labels, StatusCode::GATEWAY_TIMEOUT)),
         Err(e) => {
             error!("Failed to forward request: {}", e);
-            Err(StatusCode::BAD_GATEWAY)
+            Err(reject(&labels, StatusCode::BAD_GATEWAY))
         }
     }
 }
@@ -78,8 +133,24 @@ pub async fn health_check() -> impl IntoResponse {
     (StatusCode::OK, "OK")
 }
 
+/// Prometheus metrics endpoint
+pub async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(
+            header::CONTENT_TYPE,
+            "application/openmetrics-text; version=1.0.0; charset=utf-8",
+        )],
+        state.metrics.encode(),
+    )
+}
+
 /// Application state
 pub struct AppState {
     pub pool: SqlitePool,
     pub http_proxy: HttpProxy,
+    pub ws_proxy: WsProxy,
+    pub session_cache: SessionCache,
+    pub metrics: Arc<Metrics>,
+    pub balancer: Balancer,
+    pub rate_limiter: RateLimiter,
 }
@@ -1,5 +1,7 @@
+pub mod admin;
 pub mod http;
 pub mod websocket;
 
-pub use http::{AppState, health_check, http_proxy_handler};
+pub use admin::admin_router;
+pub use http::{AppState, health_check, http_proxy_handler, metrics_handler};
 pub use websocket::websocket_handler;
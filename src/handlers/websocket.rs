@@ -6,15 +6,22 @@ use axum::{
     http::StatusCode,
     response::Response,
 };
-use sqlx::SqlitePool;
 use std::sync::Arc;
 use tracing::{error, info, warn};
 
-use crate::{db, proxy::WsProxy};
+use crate::{
+    handlers::AppState,
+    metrics::SessionLabels,
+    proxy::{
+        DownstreamWs, WsProxy,
+        balancer::Selection,
+        ws_proxy::{ConnectionSlot, WsProxyError},
+    },
+};
 
 /// WebSocket proxy handler
 pub async fn websocket_handler(
-    State(pool): State<Arc<SqlitePool>>,
+    State(state): State<Arc<AppState>>,
     ws: WebSocketUpgrade,
     req: Request,
 ) -> Result<Response, StatusCode> {
@@ -23,6 +30,14 @@ pub async fn websocket_handler(
 
     info!("Received WebSocket connection request: path={}", full_path);
 
+    if state.ws_proxy.is_shutting_down() {
+        warn!(
+            "Rejecting WebSocket connection during shutdown: {}",
+            full_path
+        );
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
     // Extract session_id from path (format: /ws/{session_id} or /{session_id})
     let session_id = full_path
         .trim_start_matches('/')
@@ -39,7 +54,11 @@ pub async fn websocket_handler(
     info!("Extracted session_id: {}", session_id);
 
     // 1. Query database to get session information
-    let session = match db::get_session(&pool, &session_id).await {
+    let session = match state
+        .session_cache
+        .get_session(&state.pool, &session_id)
+        .await
+    {
         Ok(s) => s,
         Err(e) => {
             warn!("Session not found: {} - {}", session_id, e);
@@ -56,23 +75,101 @@ pub async fn websocket_handler(
         return Err(StatusCode::SERVICE_UNAVAILABLE);
     }
 
-    // 3. Convert downstream URL to WebSocket format and append full path
-    let downstream_ws_url = format!(
+    let labels = state.metrics.session_labels(&session);
+
+    // 3. Apply per-session rate and connection limits
+    let config = session.config();
+    if let Some(limit) = &config.rate_limit
+        && !state.rate_limiter.check(&session_id, limit)
+    {
+        warn!("Rate limit exceeded for session: {}", session_id);
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+    let Some(slot) = state
+        .ws_proxy
+        .reserve_connection(&session_id, config.websocket.max_connections)
+    else {
+        warn!(
+            "WebSocket connection limit reached for session: {}",
+            session_id
+        );
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    };
+
+    // 4. Pick a downstream from the session's upstream pool
+    let Some(upstream) = state.balancer.select(&session, req.headers()) else {
+        error!("No available upstream for session: {}", session_id);
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    };
+
+    // 5. Convert downstream URL to WebSocket format and append mapped path and query
+    let mut downstream_ws_url = format!(
         "{}{}",
-        convert_to_ws_url(&session.downstream_server_url).trim_end_matches('/'),
-        full_path
+        convert_to_ws_url(&upstream.url).trim_end_matches('/'),
+        state.ws_proxy.downstream_path(&session_id, &full_path)
     );
+    if let Some(query) = req.uri().query() {
+        downstream_ws_url.push('?');
+        downstream_ws_url.push_str(query);
+    }
     info!("Downstream WebSocket URL: {}", downstream_ws_url);
 
-    // 4. Upgrade to WebSocket connection
-    Ok(ws.on_upgrade(move |socket| handle_websocket(socket, downstream_ws_url)))
+    // 6. Select client handshake headers to forward downstream
+    let headers = state.ws_proxy.select_headers(req.headers());
+    let protocols = WsProxy::offered_protocols(req.headers());
+
+    // 7. Connect downstream first so its subprotocol choice can be relayed to the client
+    let downstream = match state
+        .ws_proxy
+        .connect(&downstream_ws_url, headers, &protocols, config)
+        .await
+    {
+        Ok(d) => d,
+        Err(WsProxyError::SubprotocolRejected(e)) => {
+            warn!(
+                "No subprotocol accepted for {} (offered {:?}): {}",
+                session_id, protocols, e
+            );
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Err(e) => {
+            error!("Failed to connect downstream WebSocket: {}", e);
+            return Err(StatusCode::BAD_GATEWAY);
+        }
+    };
+
+    // 8. Upgrade to WebSocket connection with the downstream-selected subprotocol
+    let ws = match &downstream.protocol {
+        Some(protocol) => ws.protocols([protocol.clone()]),
+        None => ws,
+    };
+    let ws = match config.websocket.max_message_size {
+        Some(size) => ws.max_message_size(size).max_frame_size(size),
+        None => ws,
+    };
+    Ok(ws.on_upgrade(move |socket| {
+        handle_websocket(state, socket, downstream, labels, upstream, slot)
+    }))
 }
 
 /// Handle WebSocket connection
-async fn handle_websocket(socket: WebSocket, downstream_url: String) {
+async fn handle_websocket(
+    state: Arc<AppState>,
+    socket: WebSocket,
+    downstream: DownstreamWs,
+    labels: SessionLabels,
+    // Held so the upstream stays counted as outstanding while connected
+    _upstream: Selection,
+    // Held so the connection counts against the session's limit while open
+    _slot: ConnectionSlot,
+) {
     info!("WebSocket connection upgraded");
 
-    if let Err(e) = WsProxy::handle_connection(socket, &downstream_url).await {
+    if let Err(e) = state
+        .ws_proxy
+        .handle_connection(socket, downstream, labels)
+        .await
+    {
         error!("WebSocket proxy error: {}", e);
     }
 
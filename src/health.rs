@@ -0,0 +1,324 @@
+use futures_util::{StreamExt, stream};
+use reqwest::{Client, StatusCode};
+use sqlx::{Error as SqlxError, sqlite::SqlitePool};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::{debug, error, info, warn};
+
+use crate::{db, handlers::AppState, shutdown::Shutdown};
+
+/// Status of a downstream that passes health checks
+pub const HEALTHY_STATUS: &str = "active";
+/// Status written when a downstream fails health checks
+pub const UNHEALTHY_STATUS: &str = "unhealthy";
+
+/// Page size used when scanning sessions to probe
+const SCAN_PAGE_SIZE: i64 = 500;
+/// Maximum number of probes in flight at once
+const MAX_CONCURRENT_PROBES: usize = 32;
+
+/// Something whose status the checker manages
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Target {
+    /// Session without upstreams, probed via `downstream_server_url`
+    Session(String),
+    /// Row in the session_upstreams table
+    Upstream { session_id: String, id: i64 },
+}
+
+/// Probe target with its URL and current status
+struct Probe {
+    target: Target,
+    url: String,
+    status: String,
+}
+
+/// Consecutive probe results for a target
+#[derive(Debug, Default)]
+struct Streak {
+    successes: u32,
+    failures: u32,
+}
+
+/// Periodically probes downstreams and flips their status between
+/// `active` and `unhealthy`
+///
+/// Only targets currently `active` or `unhealthy` are probed, so statuses set
+/// by hand (e.g. `inactive`) are left alone.
+pub struct HealthChecker {
+    client: Client,
+    interval: Duration,
+    /// Path appended to the downstream URL for each probe
+    path: String,
+    expected_status: StatusCode,
+    /// Consecutive successes needed to mark an unhealthy target active
+    healthy_threshold: u32,
+    /// Consecutive failures needed to mark an active target unhealthy
+    unhealthy_threshold: u32,
+    streaks: HashMap<Target, Streak>,
+}
+
+impl HealthChecker {
+    /// Create a new health checker probing every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            client: Self::build_client(Duration::from_secs(5)),
+            interval,
+            path: "/health".to_string(),
+            expected_status: StatusCode::OK,
+            healthy_threshold: 2,
+            unhealthy_threshold: 3,
+            streaks: HashMap::new(),
+        }
+    }
+
+    /// Set the probe path appended to the downstream URL
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = if path.starts_with('/') {
+            path.to_string()
+        } else {
+            format!("/{}", path)
+        };
+        self
+    }
+
+    /// Set the status code a healthy downstream must return
+    ///
+    /// Invalid status codes are ignored and the default is kept.
+    pub fn with_expected_status(mut self, status: u16) -> Self {
+        if let Ok(status) = StatusCode::from_u16(status) {
+            self.expected_status = status;
+        }
+        self
+    }
+
+    /// Set the per-probe timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = Self::build_client(timeout);
+        self
+    }
+
+    /// Set consecutive successes/failures needed before flipping status
+    pub fn with_thresholds(mut self, healthy: u32, unhealthy: u32) -> Self {
+        self.healthy_threshold = healthy.max(1);
+        self.unhealthy_threshold = unhealthy.max(1);
+        self
+    }
+
+    fn build_client(timeout: Duration) -> Client {
+        Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Failed to create health check client")
+    }
+
+    /// Probe all managed targets every interval until shutdown
+    pub async fn run(mut self, state: Arc<AppState>, shutdown: Shutdown) {
+        info!(
+            "🩺 Health checker started (interval: {:?}, path: {})",
+            self.interval, self.path
+        );
+
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.triggered() => break,
+            }
+
+            if let Err(e) = self.check_all(&state).await {
+                error!("Health check round failed: {}", e);
+            }
+        }
+    }
+
+    /// Run one round of probes and apply any status changes
+    async fn check_all(&mut self, state: &AppState) -> Result<(), SqlxError> {
+        let probes = collect_probes(&state.pool).await?;
+        debug!("Health check round: {} target(s)", probes.len());
+
+        let results: Vec<(Probe, bool)> = stream::iter(probes)
+            .map(|probe| async {
+                let healthy = self.probe(&probe.url).await;
+                (probe, healthy)
+            })
+            .buffer_unordered(MAX_CONCURRENT_PROBES)
+            .collect()
+            .await;
+
+        // Forget targets that are no longer managed
+        self.streaks
+            .retain(|target, _| results.iter().any(|(p, _)| &p.target == target));
+
+        for (probe, healthy) in results {
+            let Some(new_status) = self.record(&probe.target, healthy, &probe.status) else {
+                continue;
+            };
+
+            let result = match &probe.target {
+                Target::Session(session_id) => {
+                    db::update_session_status(&state.pool, session_id, new_status).await
+                }
+                Target::Upstream { id, .. } => {
+                    db::update_upstream_status(&state.pool, *id, new_status).await
+                }
+            };
+            match result {
+                Ok(()) => {
+                    let (Target::Session(session_id) | Target::Upstream { session_id, .. }) =
+                        &probe.target;
+                    state.session_cache.invalidate(session_id);
+                    if new_status == UNHEALTHY_STATUS {
+                        warn!(
+                            "Downstream {} of session {} is unhealthy",
+                            probe.url, session_id
+                        );
+                    } else {
+                        info!(
+                            "Downstream {} of session {} is healthy again",
+                            probe.url, session_id
+                        );
+                    }
+                }
+                // Deleted since the scan, nothing to update
+                Err(SqlxError::RowNotFound) => {}
+                Err(e) => error!("Failed to update status of {}: {}", probe.url, e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Probe a downstream, returning whether it answered with the expected status
+    async fn probe(&self, base_url: &str) -> bool {
+        let url = format!(
+            "{}{}",
+            to_http_url(base_url).trim_end_matches('/'),
+            self.path
+        );
+        match self.client.get(&url).send().await {
+            Ok(response) if response.status() == self.expected_status => true,
+            Ok(response) => {
+                debug!("Health probe {} returned {}", url, response.status());
+                false
+            }
+            Err(e) => {
+                debug!("Health probe {} failed: {}", url, e);
+                false
+            }
+        }
+    }
+
+    /// Update the streak for a target, returning the status to write when it
+    /// crosses a threshold
+    fn record(&mut self, target: &Target, healthy: bool, status: &str) -> Option<&'static str> {
+        let streak = self.streaks.entry(target.clone()).or_default();
+        if healthy {
+            streak.successes += 1;
+            streak.failures = 0;
+            (status == UNHEALTHY_STATUS && streak.successes >= self.healthy_threshold)
+                .then_some(HEALTHY_STATUS)
+        } else {
+            streak.failures += 1;
+            streak.successes = 0;
+            (status == HEALTHY_STATUS && streak.failures >= self.unhealthy_threshold)
+                .then_some(UNHEALTHY_STATUS)
+        }
+    }
+}
+
+/// Gather every managed session and upstream
+///
+/// Sessions with upstreams are probed per upstream, since their own URL does
+/// not receive traffic.
+async fn collect_probes(pool: &SqlitePool) -> Result<Vec<Probe>, SqlxError> {
+    let mut probes = Vec::new();
+
+    for status in [HEALTHY_STATUS, UNHEALTHY_STATUS] {
+        let mut offset = 0;
+        loop {
+            let page = db::list_sessions(pool, Some(status), SCAN_PAGE_SIZE, offset).await?;
+            let page_len = page.len() as i64;
+
+            for session in page {
+                let upstreams = db::list_upstreams(pool, &session.session_id).await?;
+                if upstreams.is_empty() {
+                    probes.push(Probe {
+                        target: Target::Session(session.session_id),
+                        url: session.downstream_server_url,
+                        status: session.downstream_server_status,
+                    });
+                    continue;
+                }
+
+                probes.extend(
+                    upstreams
+                        .into_iter()
+                        .filter(|u| matches!(u.status.as_str(), HEALTHY_STATUS | UNHEALTHY_STATUS))
+                        .map(|u| Probe {
+                            target: Target::Upstream {
+                                session_id: session.session_id.clone(),
+                                id: u.id,
+                            },
+                            url: u.upstream_url,
+                            status: u.status,
+                        }),
+                );
+            }
+
+            if page_len < SCAN_PAGE_SIZE {
+                break;
+            }
+            offset += SCAN_PAGE_SIZE;
+        }
+    }
+
+    Ok(probes)
+}
+
+/// Convert WS/WSS URLs to HTTP/HTTPS for probing
+fn to_http_url(url: &str) -> String {
+    if let Some(rest) = url.strip_prefix("wss://") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = url.strip_prefix("ws://") {
+        format!("http://{}", rest)
+    } else {
+        url.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thresholds() {
+        let mut checker = HealthChecker::new(Duration::from_secs(1)).with_thresholds(2, 3);
+        let target = Target::Session("a".to_string());
+
+        assert_eq!(checker.record(&target, false, "active"), None);
+        assert_eq!(checker.record(&target, false, "active"), None);
+        assert_eq!(checker.record(&target, true, "active"), None);
+        assert_eq!(checker.record(&target, false, "active"), None);
+        assert_eq!(checker.record(&target, false, "active"), None);
+        assert_eq!(
+            checker.record(&target, false, "active"),
+            Some(UNHEALTHY_STATUS)
+        );
+
+        assert_eq!(checker.record(&target, true, "unhealthy"), None);
+        assert_eq!(
+            checker.record(&target, true, "unhealthy"),
+            Some(HEALTHY_STATUS)
+        );
+
+        // Already in the matching state
+        assert_eq!(checker.record(&target, true, "active"), None);
+    }
+
+    #[test]
+    fn test_to_http_url() {
+        assert_eq!(to_http_url("ws://localhost:8080"), "http://localhost:8080");
+        assert_eq!(to_http_url("wss://example.com"), "https://example.com");
+        assert_eq!(to_http_url("http://localhost"), "http://localhost");
+    }
+}
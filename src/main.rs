@@ -1,22 +1,38 @@
 use axum::{
     Router,
     routing::{any, get},
+    serve::Listener,
 };
 use clap::Parser;
 use std::{sync::Arc, time::Duration};
 use tower_http::trace::TraceLayer;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod cache;
 mod config;
 mod db;
 mod handlers;
+mod health;
+mod metrics;
 mod models;
 mod proxy;
+mod shutdown;
+mod tls;
 
-use config::Config;
-use handlers::{AppState, health_check, http_proxy_handler, websocket_handler};
-use proxy::HttpProxy;
+use cache::SessionCache;
+use config::{Config, LbStrategy, WsPathMode};
+use handlers::{
+    AppState, admin_router, health_check, http_proxy_handler, metrics_handler, websocket_handler,
+};
+use health::HealthChecker;
+use metrics::Metrics;
+use proxy::{
+    Balancer, CircuitBreaker, HttpProxy, RateLimiter, RetryPolicy, WsProxy,
+    balancer::DEFAULT_HASH_HEADER,
+};
+use shutdown::Shutdown;
+use tls::{TlsConfig, TlsListener};
 
 /// SS Proxy - HTTP/HTTPS/WebSocket Proxy Server
 #[derive(Parser, Debug)]
@@ -35,13 +51,174 @@ pub struct CliArgs {
     #[arg(short, long, default_value = "./sessions.db", env = "SS_PROXY_DB_PATH")]
     pub db_path: String,
 
-    /// Request timeout in seconds
+    /// Time in seconds to wait for downstream response headers (0 disables)
     #[arg(short, long, default_value = "30", env = "SS_PROXY_TIMEOUT")]
     pub timeout: u64,
 
+    /// Downstream connect timeout in seconds (0 disables)
+    #[arg(long, default_value = "10", env = "SS_PROXY_CONNECT_TIMEOUT")]
+    pub connect_timeout: u64,
+
+    /// Maximum gap in seconds between response body chunks, e.g. SSE events (0 disables)
+    #[arg(long, default_value = "60", env = "SS_PROXY_IDLE_TIMEOUT")]
+    pub idle_timeout: u64,
+
     /// Log level (trace, debug, info, warn, error)
     #[arg(short, long, default_value = "info", env = "SS_PROXY_LOG_LEVEL")]
     pub log_level: String,
+
+    /// Bearer token required by the admin API (admin API is disabled when unset)
+    #[arg(long, env = "SS_PROXY_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+
+    /// Maximum number of cached session lookups (0 disables the cache)
+    #[arg(long, default_value = "10000", env = "SS_PROXY_SESSION_CACHE_CAPACITY")]
+    pub session_cache_capacity: usize,
+
+    /// Session cache TTL in seconds (0 disables the cache)
+    #[arg(long, default_value = "5", env = "SS_PROXY_SESSION_CACHE_TTL")]
+    pub session_cache_ttl: u64,
+
+    /// Cache TTL in seconds for unknown session IDs (0 disables negative caching)
+    #[arg(long, default_value = "1", env = "SS_PROXY_SESSION_CACHE_NEGATIVE_TTL")]
+    pub session_cache_negative_ttl: u64,
+
+    /// Maximum request body size in bytes (0 means unlimited)
+    #[arg(long, default_value = "104857600", env = "SS_PROXY_MAX_BODY_SIZE")]
+    pub max_body_size: u64,
+
+    /// Comma-separated client WebSocket handshake headers forwarded downstream
+    /// (a trailing `*` matches a prefix, e.g. `x-*`)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "authorization,cookie,user-agent,x-*",
+        env = "SS_PROXY_WS_FORWARD_HEADERS"
+    )]
+    pub ws_forward_headers: Vec<String>,
+
+    /// How the `/ws/{session_id}` prefix maps onto the downstream WebSocket path
+    #[arg(
+        long,
+        value_enum,
+        default_value = "keep",
+        env = "SS_PROXY_WS_PATH_MODE"
+    )]
+    pub ws_path_mode: WsPathMode,
+
+    /// Replacement for the `/ws/{session_id}` prefix when `--ws-path-mode replace` is used
+    #[arg(long, default_value = "", env = "SS_PROXY_WS_PATH_PREFIX")]
+    pub ws_path_prefix: String,
+
+    /// Maximum time in seconds to drain in-flight streams and WebSocket sessions on shutdown
+    #[arg(long, default_value = "30", env = "SS_PROXY_SHUTDOWN_TIMEOUT")]
+    pub shutdown_timeout: u64,
+
+    /// PEM certificate chain for serving HTTPS/WSS directly (reloaded on SIGHUP)
+    #[arg(long, env = "SS_PROXY_TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<String>,
+
+    /// PEM private key matching --tls-cert
+    #[arg(long, env = "SS_PROXY_TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<String>,
+
+    /// Number of sessions labeled individually by session_id in /metrics (0 disables the label)
+    #[arg(long, default_value = "0", env = "SS_PROXY_METRICS_SESSION_LABELS")]
+    pub metrics_session_labels: usize,
+
+    /// Strategy for spreading requests across a session's upstreams
+    #[arg(
+        long,
+        value_enum,
+        default_value = "round-robin",
+        env = "SS_PROXY_LB_STRATEGY"
+    )]
+    pub lb_strategy: LbStrategy,
+
+    /// Request header hashed by the consistent-hash strategy
+    #[arg(long, default_value = DEFAULT_HASH_HEADER, env = "SS_PROXY_LB_HASH_HEADER")]
+    pub lb_hash_header: String,
+
+    /// Interval in seconds between downstream health checks (0 disables them)
+    #[arg(long, default_value = "0", env = "SS_PROXY_HEALTH_CHECK_INTERVAL")]
+    pub health_check_interval: u64,
+
+    /// HTTP path probed on each downstream
+    #[arg(long, default_value = "/health", env = "SS_PROXY_HEALTH_CHECK_PATH")]
+    pub health_check_path: String,
+
+    /// Status code a healthy downstream must return
+    #[arg(
+        long,
+        default_value = "200",
+        env = "SS_PROXY_HEALTH_CHECK_EXPECTED_STATUS"
+    )]
+    pub health_check_expected_status: u16,
+
+    /// Health probe timeout in seconds
+    #[arg(long, default_value = "5", env = "SS_PROXY_HEALTH_CHECK_TIMEOUT")]
+    pub health_check_timeout: u64,
+
+    /// Consecutive successful probes before an unhealthy downstream is marked active
+    #[arg(
+        long,
+        default_value = "2",
+        env = "SS_PROXY_HEALTH_CHECK_HEALTHY_THRESHOLD"
+    )]
+    pub health_check_healthy_threshold: u32,
+
+    /// Consecutive failed probes before an active downstream is marked unhealthy
+    #[arg(
+        long,
+        default_value = "3",
+        env = "SS_PROXY_HEALTH_CHECK_UNHEALTHY_THRESHOLD"
+    )]
+    pub health_check_unhealthy_threshold: u32,
+
+    /// Consecutive failed requests that open a downstream's circuit breaker (0 disables)
+    #[arg(long, default_value = "5", env = "SS_PROXY_CIRCUIT_BREAKER_FAILURES")]
+    pub circuit_breaker_failures: u32,
+
+    /// Share of 5xx/failed requests within the window that opens the breaker (0 disables)
+    #[arg(
+        long,
+        default_value = "0.5",
+        env = "SS_PROXY_CIRCUIT_BREAKER_ERROR_RATE"
+    )]
+    pub circuit_breaker_error_rate: f64,
+
+    /// Number of recent requests per downstream considered for the error rate
+    #[arg(long, default_value = "20", env = "SS_PROXY_CIRCUIT_BREAKER_WINDOW")]
+    pub circuit_breaker_window: usize,
+
+    /// Seconds a circuit stays open before a trial request is let through
+    #[arg(
+        long,
+        default_value = "30",
+        env = "SS_PROXY_CIRCUIT_BREAKER_OPEN_DURATION"
+    )]
+    pub circuit_breaker_open_duration: u64,
+
+    /// Total attempts per proxied HTTP request, including the first (1 disables retries)
+    #[arg(long, default_value = "1", env = "SS_PROXY_RETRY_MAX_ATTEMPTS")]
+    pub retry_max_attempts: u32,
+
+    /// Base retry backoff in milliseconds, doubled after each retry and jittered
+    #[arg(long, default_value = "100", env = "SS_PROXY_RETRY_BACKOFF_MS")]
+    pub retry_backoff_ms: u64,
+
+    /// Comma-separated downstream response statuses that trigger a retry
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "502,503,504",
+        env = "SS_PROXY_RETRY_ON_STATUS"
+    )]
+    pub retry_on_status: Vec<u16>,
+
+    /// Largest request body in bytes buffered so it can be replayed on retry
+    #[arg(long, default_value = "1048576", env = "SS_PROXY_RETRY_MAX_BODY_SIZE")]
+    pub retry_max_body_size: u64,
 }
 
 #[tokio::main]
@@ -70,28 +247,108 @@ async fn main() -> anyhow::Result<()> {
     let pool = db::create_pool(&config.database_url()).await?;
     info!("✅ Database connection established");
 
+    // Create metrics registry
+    let metrics = Arc::new(Metrics::new(config.metrics_session_labels));
+
+    // Create per-downstream circuit breaker
+    let breaker = CircuitBreaker::new(config.circuit_breaker_failures)
+        .with_error_rate(
+            config.circuit_breaker_error_rate,
+            config.circuit_breaker_window,
+        )
+        .with_open_duration(Duration::from_secs(config.circuit_breaker_open_duration))
+        .with_metrics(metrics.clone());
+
+    // Create retry policy for failed downstream requests
+    let retry = RetryPolicy::new(config.retry_max_attempts)
+        .with_backoff(Duration::from_millis(config.retry_backoff_ms))
+        .with_statuses(&config.retry_on_status)
+        .with_max_body_size(config.retry_max_body_size);
+
     // Create HTTP proxy client
-    let http_proxy = HttpProxy::new(Duration::from_secs(config.request_timeout));
+    let http_proxy = HttpProxy::new(Duration::from_secs(config.request_timeout))
+        .with_connect_timeout(Duration::from_secs(config.connect_timeout))
+        .with_idle_timeout(Duration::from_secs(config.idle_timeout))
+        .with_metrics(metrics.clone())
+        .with_circuit_breaker(breaker)
+        .with_retry_policy(retry)
+        .with_max_body_size(config.max_body_size);
+
+    // Create shutdown coordinator
+    let shutdown = Shutdown::new();
+
+    // Create WebSocket proxy
+    let ws_proxy = WsProxy::new()
+        .with_shutdown(shutdown.clone())
+        .with_metrics(metrics.clone())
+        .with_forward_headers(&config.ws_forward_headers)
+        .with_path_mode(config.ws_path_mode, &config.ws_path_prefix);
+
+    // Create session lookup cache
+    let session_cache = SessionCache::new(
+        config.session_cache_capacity,
+        Duration::from_secs(config.session_cache_ttl),
+        Duration::from_secs(config.session_cache_negative_ttl),
+    );
+
+    // Create upstream load balancer
+    let balancer = Balancer::new(config.lb_strategy).with_hash_header(&config.lb_hash_header);
 
     // Create shared state
-    let http_state = Arc::new(AppState {
-        pool: pool.clone(),
+    let state = Arc::new(AppState {
+        pool,
         http_proxy,
+        ws_proxy,
+        session_cache,
+        metrics,
+        balancer,
+        rate_limiter: RateLimiter::new(),
     });
-    let ws_state = Arc::new(pool);
+
+    // Start downstream health checker
+    if config.health_check_interval > 0 {
+        let checker = HealthChecker::new(Duration::from_secs(config.health_check_interval))
+            .with_path(&config.health_check_path)
+            .with_expected_status(config.health_check_expected_status)
+            .with_timeout(Duration::from_secs(config.health_check_timeout))
+            .with_thresholds(
+                config.health_check_healthy_threshold,
+                config.health_check_unhealthy_threshold,
+            );
+        tokio::spawn(checker.run(state.clone(), shutdown.clone()));
+    }
 
     // Build router
-    let app = Router::new()
+    let mut app = Router::new()
         // Health check endpoint
         .route("/health", get(health_check))
-        // WebSocket proxy: /ws/{session_id}
+        // Prometheus metrics endpoint
+        .route("/metrics", get(metrics_handler))
+        // WebSocket proxy: /ws/{session_id} and /ws/{session_id}/{*path}
         .route("/ws/{session_id}", get(websocket_handler))
-        .with_state(ws_state)
+        .route("/ws/{session_id}/{*path}", get(websocket_handler))
         // HTTP/HTTPS proxy: /{session_id}/{*path}
         .route("/{session_id}/{*path}", any(http_proxy_handler))
-        .with_state(http_state)
-        // Add request tracing
-        .layer(TraceLayer::new_for_http());
+        .with_state(state.clone());
+
+    // Admin API: /admin/sessions (only when a token is configured)
+    if let Some(token) = config.admin_token.clone() {
+        app = app.merge(admin_router(state, token.expose().to_string()));
+        info!("🔑 Admin API enabled at /admin/sessions");
+    }
+
+    // Add request tracing
+    let app = app.layer(TraceLayer::new_for_http());
+
+    // Load TLS certificate (plain HTTP when not configured)
+    let tls = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => {
+            let tls = TlsConfig::load(cert, key)?;
+            info!("🔐 TLS enabled with certificate {}", cert);
+            Some(tls)
+        }
+        _ => None,
+    };
 
     // Start server
     let addr = config.bind_address();
@@ -100,7 +357,74 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     info!("✅ Server started successfully!");
 
-    axum::serve(listener, app).await?;
+    // Trigger shutdown on SIGINT/SIGTERM
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown::wait_for_signal().await;
+            info!("🛑 Shutting down, draining connections...");
+            shutdown.trigger();
+        }
+    });
+
+    let drain_timeout = Duration::from_secs(config.shutdown_timeout);
+    match tls {
+        Some(tls) => {
+            #[cfg(unix)]
+            tokio::spawn(tls::reload_on_sighup(tls.clone()));
+            serve(
+                TlsListener::new(listener, tls),
+                app,
+                shutdown,
+                drain_timeout,
+            )
+            .await?;
+        }
+        None => serve(listener, app, shutdown, drain_timeout).await?,
+    }
+
+    Ok(())
+}
+
+/// Serve until shutdown is triggered, then wait for in-flight responses and
+/// WebSocket sessions up to the drain deadline
+async fn serve<L>(
+    listener: L,
+    app: Router,
+    shutdown: Shutdown,
+    drain_timeout: Duration,
+) -> anyhow::Result<()>
+where
+    L: Listener,
+    L::Addr: std::fmt::Debug,
+{
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move { shutdown.triggered().await }
+    });
+    let drain = async {
+        server.await?;
+        shutdown.drained().await;
+        anyhow::Ok(())
+    };
+    let deadline = async {
+        shutdown.triggered().await;
+        tokio::time::sleep(drain_timeout).await;
+    };
+
+    tokio::select! {
+        result = drain => {
+            result?;
+            info!("✅ All connections drained, server stopped");
+        }
+        _ = deadline => {
+            warn!(
+                "Drain deadline of {}s exceeded with {} WebSocket session(s) still active, exiting",
+                drain_timeout.as_secs(),
+                shutdown.active()
+            );
+        }
+    }
 
     Ok(())
 }
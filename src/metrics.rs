@@ -0,0 +1,427 @@
+use axum::http::StatusCode;
+use prometheus_client::{
+    encoding::{EncodeLabelSet, EncodeLabelValue, LabelValueEncoder, text::encode},
+    metrics::{
+        counter::Counter,
+        family::Family,
+        gauge::Gauge,
+        histogram::{Histogram, exponential_buckets},
+    },
+    registry::{Registry, Unit},
+};
+use std::{collections::HashSet, fmt, sync::Mutex, time::Duration};
+
+use crate::{models::Session, proxy::circuit_breaker::CircuitState};
+
+/// `session_id` label value used once the cardinality cap is reached
+const OTHER_SESSIONS: &str = "other";
+/// `session_status` label value for requests whose session could not be resolved
+const UNKNOWN_STATUS: &str = "unknown";
+
+/// Labels identifying the session a request was proxied for
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct SessionLabels {
+    pub session_status: String,
+    /// Only set when per-session labels are enabled
+    pub session_id: Option<String>,
+}
+
+impl SessionLabels {
+    /// Labels for requests that did not resolve to a session
+    pub fn unknown() -> Self {
+        Self {
+            session_status: UNKNOWN_STATUS.to_string(),
+            session_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RequestLabels {
+    session_status: String,
+    session_id: Option<String>,
+    status_class: &'static str,
+}
+
+/// Direction of a relayed WebSocket message
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum WsDirection {
+    ClientToDownstream,
+    DownstreamToClient,
+}
+
+impl EncodeLabelValue for WsDirection {
+    fn encode(&self, encoder: &mut LabelValueEncoder) -> fmt::Result {
+        let value = match self {
+            Self::ClientToDownstream => "client_to_downstream",
+            Self::DownstreamToClient => "downstream_to_client",
+        };
+        EncodeLabelValue::encode(&value, encoder)
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct WsMessageLabels {
+    session_status: String,
+    session_id: Option<String>,
+    direction: WsDirection,
+}
+
+impl EncodeLabelValue for CircuitState {
+    fn encode(&self, encoder: &mut LabelValueEncoder) -> fmt::Result {
+        let value = match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half_open",
+        };
+        EncodeLabelValue::encode(&value, encoder)
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct CircuitLabels {
+    state: CircuitState,
+}
+
+/// Prometheus metrics for proxied HTTP and WebSocket traffic
+pub struct Metrics {
+    registry: Registry,
+    http_requests: Family<RequestLabels, Counter>,
+    http_ttfb: Family<SessionLabels, Histogram>,
+    http_duration: Family<SessionLabels, Histogram>,
+    http_request_bytes: Family<SessionLabels, Counter>,
+    http_response_bytes: Family<SessionLabels, Counter>,
+    ws_connections: Family<SessionLabels, Counter>,
+    ws_active: Family<SessionLabels, Gauge>,
+    ws_messages: Family<WsMessageLabels, Counter>,
+    circuit_transitions: Family<CircuitLabels, Counter>,
+    circuits: Family<CircuitLabels, Gauge>,
+    circuit_rejections: Counter,
+    /// Maximum number of distinct `session_id` label values (0 disables the label)
+    max_session_labels: usize,
+    labeled_sessions: Mutex<HashSet<String>>,
+}
+
+impl Metrics {
+    /// Create a new metrics registry
+    ///
+    /// Up to `max_session_labels` sessions get their own `session_id` label;
+    /// later sessions are reported as `other`. Zero disables the label.
+    pub fn new(max_session_labels: usize) -> Self {
+        let mut registry = Registry::with_prefix("ss_proxy");
+
+        let http_requests = Family::<RequestLabels, Counter>::default();
+        registry.register(
+            "http_requests",
+            "Proxied HTTP requests by response status class",
+            http_requests.clone(),
+        );
+
+        let http_ttfb = Family::<SessionLabels, Histogram>::new_with_constructor(
+            latency_histogram as fn() -> _,
+        );
+        registry.register_with_unit(
+            "http_time_to_first_byte",
+            "Time until downstream response headers were received",
+            Unit::Seconds,
+            http_ttfb.clone(),
+        );
+
+        let http_duration = Family::<SessionLabels, Histogram>::new_with_constructor(
+            latency_histogram as fn() -> _,
+        );
+        registry.register_with_unit(
+            "http_request_duration",
+            "Time until the response body was fully sent",
+            Unit::Seconds,
+            http_duration.clone(),
+        );
+
+        let http_request_bytes = Family::<SessionLabels, Counter>::default();
+        registry.register_with_unit(
+            "http_request",
+            "Request body bytes forwarded downstream",
+            Unit::Bytes,
+            http_request_bytes.clone(),
+        );
+
+        let http_response_bytes = Family::<SessionLabels, Counter>::default();
+        registry.register_with_unit(
+            "http_response",
+            "Response body bytes returned to clients",
+            Unit::Bytes,
+            http_response_bytes.clone(),
+        );
+
+        let ws_connections = Family::<SessionLabels, Counter>::default();
+        registry.register(
+            "websocket_connections",
+            "Proxied WebSocket connections",
+            ws_connections.clone(),
+        );
+
+        let ws_active = Family::<SessionLabels, Gauge>::default();
+        registry.register(
+            "websocket_connections_active",
+            "Currently open WebSocket connections",
+            ws_active.clone(),
+        );
+
+        let ws_messages = Family::<WsMessageLabels, Counter>::default();
+        registry.register(
+            "websocket_messages",
+            "Relayed WebSocket text and binary messages",
+            ws_messages.clone(),
+        );
+
+        let circuit_transitions = Family::<CircuitLabels, Counter>::default();
+        registry.register(
+            "circuit_breaker_transitions",
+            "Circuit breaker state changes by new state",
+            circuit_transitions.clone(),
+        );
+
+        let circuits = Family::<CircuitLabels, Gauge>::default();
+        registry.register(
+            "circuit_breakers",
+            "Downstreams whose circuit breaker is currently open or half-open",
+            circuits.clone(),
+        );
+
+        let circuit_rejections = Counter::default();
+        registry.register(
+            "circuit_breaker_rejected_requests",
+            "Requests rejected because the downstream's circuit was open",
+            circuit_rejections.clone(),
+        );
+
+        Self {
+            registry,
+            http_requests,
+            http_ttfb,
+            http_duration,
+            http_request_bytes,
+            http_response_bytes,
+            ws_connections,
+            ws_active,
+            ws_messages,
+            circuit_transitions,
+            circuits,
+            circuit_rejections,
+            max_session_labels,
+            labeled_sessions: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Build labels for a session, applying the `session_id` cardinality cap
+    pub fn session_labels(&self, session: &Session) -> SessionLabels {
+        let session_id = (self.max_session_labels > 0).then(|| {
+            let mut labeled = self.labeled_sessions.lock().unwrap();
+            if labeled.contains(&session.session_id) || labeled.len() < self.max_session_labels {
+                labeled.insert(session.session_id.clone());
+                session.session_id.clone()
+            } else {
+                OTHER_SESSIONS.to_string()
+            }
+        });
+
+        SessionLabels {
+            session_status: session.downstream_server_status.clone(),
+            session_id,
+        }
+    }
+
+    /// Count a completed HTTP request by response status class
+    pub fn record_http_request(&self, labels: &SessionLabels, status: StatusCode) {
+        self.http_requests
+            .get_or_create(&RequestLabels {
+                session_status: labels.session_status.clone(),
+                session_id: labels.session_id.clone(),
+                status_class: status_class(status),
+            })
+            .inc();
+    }
+
+    /// Record the time until downstream response headers arrived
+    pub fn observe_http_ttfb(&self, labels: &SessionLabels, elapsed: Duration) {
+        self.http_ttfb
+            .get_or_create(labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Record the time until the response was fully sent
+    pub fn observe_http_duration(&self, labels: &SessionLabels, elapsed: Duration) {
+        self.http_duration
+            .get_or_create(labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Count request body bytes forwarded downstream
+    pub fn add_http_request_bytes(&self, labels: &SessionLabels, bytes: u64) {
+        self.http_request_bytes.get_or_create(labels).inc_by(bytes);
+    }
+
+    /// Count response body bytes returned to the client
+    pub fn add_http_response_bytes(&self, labels: &SessionLabels, bytes: u64) {
+        self.http_response_bytes.get_or_create(labels).inc_by(bytes);
+    }
+
+    /// Count a WebSocket connection as open until the returned guard is dropped
+    pub fn track_ws_connection(&self, labels: &SessionLabels) -> WsConnectionGuard {
+        self.ws_connections.get_or_create(labels).inc();
+        let active = self.ws_active.get_or_create(labels).clone();
+        active.inc();
+        WsConnectionGuard { active }
+    }
+
+    /// Count a relayed WebSocket message
+    pub fn record_ws_message(&self, labels: &SessionLabels, direction: WsDirection) {
+        self.ws_messages
+            .get_or_create(&WsMessageLabels {
+                session_status: labels.session_status.clone(),
+                session_id: labels.session_id.clone(),
+                direction,
+            })
+            .inc();
+    }
+
+    /// Record a circuit breaker state change
+    pub fn record_circuit_transition(&self, from: CircuitState, to: CircuitState) {
+        self.circuit_transitions
+            .get_or_create(&CircuitLabels { state: to })
+            .inc();
+        // Closed circuits are not counted, there is one per downstream seen
+        if from != CircuitState::Closed {
+            self.circuits
+                .get_or_create(&CircuitLabels { state: from })
+                .dec();
+        }
+        if to != CircuitState::Closed {
+            self.circuits
+                .get_or_create(&CircuitLabels { state: to })
+                .inc();
+        }
+    }
+
+    /// Count a request rejected by an open circuit
+    pub fn record_circuit_rejection(&self) {
+        self.circuit_rejections.inc();
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn encode(&self) -> String {
+        let mut buffer = String::new();
+        encode(&mut buffer, &self.registry).expect("Writing to a String cannot fail");
+        buffer
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Keeps a WebSocket connection counted as active while alive
+pub struct WsConnectionGuard {
+    active: Gauge,
+}
+
+impl Drop for WsConnectionGuard {
+    fn drop(&mut self) {
+        self.active.dec();
+    }
+}
+
+/// Latency buckets from 5ms to ~80s
+fn latency_histogram() -> Histogram {
+    Histogram::new(exponential_buckets(0.005, 2.0, 15))
+}
+
+/// Map a status code to its `2xx`-style class
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str) -> Session {
+        Session {
+            session_id: id.to_string(),
+            downstream_server_url: "http://localhost:8080".to_string(),
+            downstream_server_status: "active".to_string(),
+            config: None,
+            upstreams: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_session_label_cap() {
+        let metrics = Metrics::new(2);
+        assert_eq!(
+            metrics.session_labels(&session("a")).session_id.unwrap(),
+            "a"
+        );
+        assert_eq!(
+            metrics.session_labels(&session("b")).session_id.unwrap(),
+            "b"
+        );
+        assert_eq!(
+            metrics.session_labels(&session("c")).session_id.unwrap(),
+            "other"
+        );
+        assert_eq!(
+            metrics.session_labels(&session("a")).session_id.unwrap(),
+            "a"
+        );
+
+        let metrics = Metrics::default();
+        assert!(metrics.session_labels(&session("a")).session_id.is_none());
+    }
+
+    #[test]
+    fn test_encode() {
+        let metrics = Metrics::new(10);
+        let labels = metrics.session_labels(&session("a"));
+        metrics.record_http_request(&labels, StatusCode::OK);
+        metrics.record_http_request(&SessionLabels::unknown(), StatusCode::NOT_FOUND);
+        metrics.add_http_response_bytes(&labels, 42);
+        metrics.record_ws_message(&labels, WsDirection::ClientToDownstream);
+        let guard = metrics.track_ws_connection(&labels);
+
+        let output = metrics.encode();
+        assert!(output.contains(
+            r#"ss_proxy_http_requests_total{session_status="active",session_id="a",status_class="2xx"} 1"#
+        ));
+        assert!(output.contains(
+            r#"ss_proxy_http_requests_total{session_status="unknown",session_id="",status_class="4xx"} 1"#
+        ));
+        assert!(output.contains(
+            r#"ss_proxy_http_response_bytes_total{session_status="active",session_id="a"} 42"#
+        ));
+        assert!(output.contains(r#"direction="client_to_downstream"} 1"#));
+        assert!(output.contains(
+            r#"ss_proxy_websocket_connections_active{session_status="active",session_id="a"} 1"#
+        ));
+
+        metrics.record_circuit_transition(CircuitState::Closed, CircuitState::Open);
+        metrics.record_circuit_rejection();
+        let output = metrics.encode();
+        assert!(output.contains(r#"ss_proxy_circuit_breakers{state="open"} 1"#));
+        assert!(output.contains(r#"ss_proxy_circuit_breaker_transitions_total{state="open"} 1"#));
+        assert!(output.contains("ss_proxy_circuit_breaker_rejected_requests_total 1"));
+
+        drop(guard);
+        assert!(metrics.encode().contains(
+            r#"ss_proxy_websocket_connections_active{session_status="active",session_id="a"} 0"#
+        ));
+    }
+}
@@ -1,5 +1,23 @@
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{FromRow, types::Json};
+use std::collections::BTreeMap;
+
+/// Configuration used by sessions without a `config` column
+static DEFAULT_SESSION_CONFIG: SessionConfig = SessionConfig {
+    timeouts: Timeouts {
+        connect: None,
+        first_byte: None,
+        idle: None,
+    },
+    max_body_size: None,
+    headers: BTreeMap::new(),
+    allowed_methods: None,
+    rate_limit: None,
+    websocket: WebSocketLimits {
+        max_connections: None,
+        max_message_size: None,
+    },
+};
 
 /// Session information, corresponds to the sessions table in the database
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -10,18 +28,136 @@ pub struct Session {
     pub downstream_server_url: String,
     /// Downstream server status
     pub downstream_server_status: String,
+    /// Per-session overrides of global settings, stored as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Json<SessionConfig>>,
+    /// Replica pool from the session_upstreams table; when empty,
+    /// `downstream_server_url` is used directly
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upstreams: Vec<Upstream>,
 }
 
 impl Session {
     /// Check if the downstream server is available
     pub fn is_available(&self) -> bool {
-        matches!(
-            self.downstream_server_status.as_str(),
-            "active" | "online" | "ready"
-        )
+        is_available_status(&self.downstream_server_status)
+    }
+
+    /// Per-session overrides, empty when the `config` column is unset
+    pub fn config(&self) -> &SessionConfig {
+        self.config.as_deref().unwrap_or(&DEFAULT_SESSION_CONFIG)
+    }
+}
+
+/// Per-session overrides, stored in the `config` column of the sessions table
+///
+/// Unset fields fall back to the global configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionConfig {
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub timeouts: Timeouts,
+    /// Maximum request body size in bytes (0 means unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_body_size: Option<u64>,
+    /// Headers added to every downstream request, replacing client values
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// HTTP methods accepted for this session (all when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_methods: Option<Vec<String>>,
+    /// Request rate limit shared by HTTP requests and WebSocket handshakes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub websocket: WebSocketLimits,
+}
+
+impl SessionConfig {
+    /// Check whether an HTTP method is accepted for this session
+    pub fn allows_method(&self, method: &str) -> bool {
+        self.allowed_methods.as_ref().is_none_or(|methods| {
+            methods
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(method))
+        })
+    }
+}
+
+/// Downstream timeouts in seconds (0 disables the timeout)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timeouts {
+    /// Time to establish the downstream connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect: Option<u64>,
+    /// Time until downstream response headers are received
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_byte: Option<u64>,
+    /// Maximum gap between response body chunks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle: Option<u64>,
+}
+
+/// Token bucket refilled at `requests_per_second`, holding up to `burst` requests
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Sustained request rate
+    pub requests_per_second: f64,
+    /// Requests allowed in a burst (defaults to one second's worth)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+}
+
+impl RateLimit {
+    /// Bucket capacity, at least one request
+    pub fn capacity(&self) -> f64 {
+        self.burst
+            .map(f64::from)
+            .unwrap_or(self.requests_per_second.ceil())
+            .max(1.0)
     }
 }
 
+/// WebSocket limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebSocketLimits {
+    /// Concurrent connections allowed for the session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<u32>,
+    /// Largest message accepted from either peer, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_message_size: Option<usize>,
+}
+
+/// Downstream replica, corresponds to the session_upstreams table in the database
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Upstream {
+    /// Upstream ID
+    pub id: i64,
+    /// Upstream server URL
+    pub upstream_url: String,
+    /// Relative weight for weighted and consistent-hash balancing
+    pub weight: i64,
+    /// Upstream server status
+    pub status: String,
+}
+
+impl Upstream {
+    /// Check if the upstream server is available
+    pub fn is_available(&self) -> bool {
+        self.weight > 0 && is_available_status(&self.status)
+    }
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// Statuses that mark a downstream server as able to receive traffic
+fn is_available_status(status: &str) -> bool {
+    matches!(status, "active" | "online" | "ready")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -32,6 +168,8 @@ mod tests {
             session_id: "test".to_string(),
             downstream_server_url: "http://localhost:8080".to_string(),
             downstream_server_status: "active".to_string(),
+            config: None,
+            upstreams: Vec::new(),
         };
         assert!(session.is_available());
 
@@ -39,7 +177,58 @@ mod tests {
             session_id: "test".to_string(),
             downstream_server_url: "http://localhost:8080".to_string(),
             downstream_server_status: "inactive".to_string(),
+            config: None,
+            upstreams: Vec::new(),
         };
         assert!(!inactive.is_available());
     }
+
+    #[test]
+    fn test_session_config() {
+        let config: SessionConfig =
+            serde_json::from_str(r#"{"timeouts": {"first_byte": 300, "idle": 0}}"#).unwrap();
+        assert_eq!(
+            config.timeouts,
+            Timeouts {
+                connect: None,
+                first_byte: Some(300),
+                idle: Some(0),
+            }
+        );
+
+        let config: SessionConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.timeouts, Timeouts::default());
+        assert!(config.allows_method("DELETE"));
+
+        let config: SessionConfig = serde_json::from_str(
+            r#"{
+                "max_body_size": 1024,
+                "headers": {"x-tenant": "acme"},
+                "allowed_methods": ["GET", "post"],
+                "rate_limit": {"requests_per_second": 2.5},
+                "websocket": {"max_connections": 3}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.max_body_size, Some(1024));
+        assert_eq!(config.headers["x-tenant"], "acme");
+        assert!(config.allows_method("POST"));
+        assert!(!config.allows_method("DELETE"));
+        assert_eq!(config.rate_limit.unwrap().capacity(), 3.0);
+        assert_eq!(config.websocket.max_connections, Some(3));
+        assert_eq!(config.websocket.max_message_size, None);
+    }
+
+    #[test]
+    fn test_upstream_is_available() {
+        let upstream = |weight, status: &str| Upstream {
+            id: 1,
+            upstream_url: "http://localhost:8080".to_string(),
+            weight,
+            status: status.to_string(),
+        };
+        assert!(upstream(1, "online").is_available());
+        assert!(!upstream(0, "active").is_available());
+        assert!(!upstream(1, "down").is_available());
+    }
 }
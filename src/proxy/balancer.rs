@@ -0,0 +1,297 @@
+use axum::http::{HeaderMap, HeaderName};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tracing::debug;
+
+use crate::{
+    config::LbStrategy,
+    models::{Session, Upstream},
+};
+
+/// Default header hashed by `LbStrategy::ConsistentHash`
+pub const DEFAULT_HASH_HEADER: &str = "x-affinity-key";
+
+/// Picks a downstream URL for each request from a session's upstream pool
+pub struct Balancer {
+    strategy: LbStrategy,
+    /// Header whose value is hashed by `LbStrategy::ConsistentHash`
+    hash_header: HeaderName,
+    /// Per-session round-robin counters
+    cursors: Mutex<HashMap<String, usize>>,
+    /// Per-session smooth weighted round-robin state, keyed by upstream ID
+    current_weights: Mutex<HashMap<String, HashMap<i64, i64>>>,
+    /// In-flight requests and connections per upstream ID
+    outstanding: Arc<Mutex<HashMap<i64, usize>>>,
+}
+
+/// Downstream chosen for a request
+///
+/// Keeps the upstream counted as outstanding until dropped, so it should be
+/// held for as long as the response or connection is in use.
+pub struct Selection {
+    pub url: String,
+    _lease: Option<Lease>,
+}
+
+/// Decrements the outstanding count of an upstream on drop
+struct Lease {
+    upstream_id: i64,
+    outstanding: Arc<Mutex<HashMap<i64, usize>>>,
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let mut outstanding = self.outstanding.lock().unwrap();
+        if let Some(count) = outstanding.get_mut(&self.upstream_id) {
+            *count -= 1;
+            if *count == 0 {
+                outstanding.remove(&self.upstream_id);
+            }
+        }
+    }
+}
+
+impl Balancer {
+    /// Create a new balancer
+    pub fn new(strategy: LbStrategy) -> Self {
+        Self {
+            strategy,
+            hash_header: HeaderName::from_static(DEFAULT_HASH_HEADER),
+            cursors: Mutex::new(HashMap::new()),
+            current_weights: Mutex::new(HashMap::new()),
+            outstanding: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Set the header hashed by the consistent-hash strategy
+    ///
+    /// Invalid header names are ignored and the default is kept.
+    pub fn with_hash_header(mut self, header: &str) -> Self {
+        if let Ok(name) = HeaderName::try_from(header.trim()) {
+            self.hash_header = name;
+        }
+        self
+    }
+
+    /// Choose the downstream for a request
+    ///
+    /// Sessions without upstreams always use `downstream_server_url`. Returns
+    /// `None` when the session has upstreams but none of them is available.
+    pub fn select(&self, session: &Session, headers: &HeaderMap) -> Option<Selection> {
+        if session.upstreams.is_empty() {
+            return Some(Selection {
+                url: session.downstream_server_url.clone(),
+                _lease: None,
+            });
+        }
+
+        let available: Vec<&Upstream> = session
+            .upstreams
+            .iter()
+            .filter(|u| u.is_available())
+            .collect();
+        if available.is_empty() {
+            return None;
+        }
+
+        let affinity_key = headers.get(&self.hash_header).map(|value| value.as_bytes());
+
+        let upstream = match (self.strategy, affinity_key) {
+            (LbStrategy::RoundRobin, _) | (LbStrategy::ConsistentHash, None) => {
+                self.round_robin(&session.session_id, &available)
+            }
+            (LbStrategy::Weighted, _) => self.weighted(&session.session_id, &available),
+            (LbStrategy::LeastOutstanding, _) => self.least_outstanding(&available),
+            (LbStrategy::ConsistentHash, Some(key)) => consistent_hash(key, &available),
+        };
+        debug!(
+            "Selected upstream {} for session {}",
+            upstream.upstream_url, session.session_id
+        );
+
+        Some(Selection {
+            url: upstream.upstream_url.clone(),
+            _lease: Some(self.lease(upstream.id)),
+        })
+    }
+
+    /// Cycle through available upstreams in order
+    fn round_robin<'a>(&self, session_id: &str, available: &[&'a Upstream]) -> &'a Upstream {
+        let mut cursors = self.cursors.lock().unwrap();
+        let cursor = cursors.entry(session_id.to_string()).or_default();
+        let upstream = available[*cursor % available.len()];
+        *cursor = cursor.wrapping_add(1);
+        upstream
+    }
+
+    /// Smooth weighted round-robin: each upstream gets a share of requests
+    /// proportional to its weight, interleaved rather than in bursts
+    fn weighted<'a>(&self, session_id: &str, available: &[&'a Upstream]) -> &'a Upstream {
+        let mut sessions = self.current_weights.lock().unwrap();
+        let current = sessions.entry(session_id.to_string()).or_default();
+        current.retain(|id, _| available.iter().any(|u| u.id == *id));
+
+        let total: i64 = available.iter().map(|u| u.weight).sum();
+        let mut best = available[0];
+        let mut best_weight = i64::MIN;
+        for upstream in available {
+            let weight = current.entry(upstream.id).or_default();
+            *weight += upstream.weight;
+            if *weight > best_weight {
+                best = upstream;
+                best_weight = *weight;
+            }
+        }
+        *current.get_mut(&best.id).unwrap() -= total;
+        best
+    }
+
+    /// Pick the upstream with the fewest in-flight requests relative to its weight
+    fn least_outstanding<'a>(&self, available: &[&'a Upstream]) -> &'a Upstream {
+        let outstanding = self.outstanding.lock().unwrap();
+        let load =
+            |u: &Upstream| outstanding.get(&u.id).copied().unwrap_or(0) as f64 / u.weight as f64;
+        available
+            .iter()
+            .copied()
+            .min_by(|a, b| load(a).total_cmp(&load(b)))
+            .expect("available is not empty")
+    }
+
+    /// Count an upstream as outstanding until the lease is dropped
+    fn lease(&self, upstream_id: i64) -> Lease {
+        *self
+            .outstanding
+            .lock()
+            .unwrap()
+            .entry(upstream_id)
+            .or_default() += 1;
+        Lease {
+            upstream_id,
+            outstanding: self.outstanding.clone(),
+        }
+    }
+}
+
+/// Weighted rendezvous hashing: the same key maps to the same upstream, and only
+/// keys owned by an upstream move when it becomes unavailable
+fn consistent_hash<'a>(key: &[u8], available: &[&'a Upstream]) -> &'a Upstream {
+    let score = |u: &Upstream| {
+        let hash = hash_key(&[key, b"\0", u.upstream_url.as_bytes()].concat());
+        // Map the hash into (0, 1) and scale by weight
+        let unit = (hash >> 11) as f64 / (1u64 << 53) as f64;
+        -(u.weight as f64) / unit.max(f64::MIN_POSITIVE).ln()
+    };
+    available
+        .iter()
+        .copied()
+        .max_by(|a, b| score(a).total_cmp(&score(b)))
+        .expect("available is not empty")
+}
+
+/// 64-bit FNV-1a with a splitmix64 finalizer, stable across processes so
+/// replicas of the proxy agree
+fn hash_key(data: &[u8]) -> u64 {
+    let hash = data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    });
+    let hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn session(upstreams: &[(i64, i64, &str)]) -> Session {
+        Session {
+            session_id: "s".to_string(),
+            downstream_server_url: "http://fallback".to_string(),
+            downstream_server_status: "active".to_string(),
+            config: None,
+            upstreams: upstreams
+                .iter()
+                .map(|(id, weight, status)| Upstream {
+                    id: *id,
+                    upstream_url: format!("http://u{}", id),
+                    weight: *weight,
+                    status: status.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn pick(balancer: &Balancer, session: &Session) -> String {
+        balancer.select(session, &HeaderMap::new()).unwrap().url
+    }
+
+    #[test]
+    fn test_no_upstreams_uses_session_url() {
+        let balancer = Balancer::new(LbStrategy::RoundRobin);
+        assert_eq!(pick(&balancer, &session(&[])), "http://fallback");
+    }
+
+    #[test]
+    fn test_round_robin_skips_unavailable() {
+        let balancer = Balancer::new(LbStrategy::RoundRobin);
+        let session = session(&[(1, 1, "active"), (2, 1, "inactive"), (3, 1, "online")]);
+        let picks: Vec<_> = (0..4).map(|_| pick(&balancer, &session)).collect();
+        assert_eq!(picks, ["http://u1", "http://u3", "http://u1", "http://u3"]);
+
+        let down = super::tests::session(&[(1, 1, "inactive")]);
+        assert!(balancer.select(&down, &HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_weighted() {
+        let balancer = Balancer::new(LbStrategy::Weighted);
+        let session = session(&[(1, 3, "active"), (2, 1, "active")]);
+        let picks: Vec<_> = (0..4).map(|_| pick(&balancer, &session)).collect();
+        assert_eq!(picks, ["http://u1", "http://u1", "http://u2", "http://u1"]);
+    }
+
+    #[test]
+    fn test_least_outstanding() {
+        let balancer = Balancer::new(LbStrategy::LeastOutstanding);
+        let session = session(&[(1, 1, "active"), (2, 1, "active")]);
+
+        let first = balancer.select(&session, &HeaderMap::new()).unwrap();
+        assert_eq!(first.url, "http://u1");
+        let second = balancer.select(&session, &HeaderMap::new()).unwrap();
+        assert_eq!(second.url, "http://u2");
+
+        drop(first);
+        assert_eq!(pick(&balancer, &session), "http://u1");
+        drop(second);
+        assert!(balancer.outstanding.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_consistent_hash() {
+        let balancer = Balancer::new(LbStrategy::ConsistentHash).with_hash_header("x-user");
+        let all = session(&[(1, 1, "active"), (2, 1, "active"), (3, 1, "active")]);
+
+        let mut moved = 0;
+        for user in 0..100 {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-user", HeaderValue::from_str(&user.to_string()).unwrap());
+            let owner = balancer.select(&all, &headers).unwrap().url;
+            assert_eq!(balancer.select(&all, &headers).unwrap().url, owner);
+
+            // Only keys owned by the removed upstream are remapped
+            let without_u2 = session(&[(1, 1, "active"), (2, 1, "down"), (3, 1, "active")]);
+            let remapped = balancer.select(&without_u2, &headers).unwrap().url;
+            if owner == "http://u2" {
+                moved += 1;
+            } else {
+                assert_eq!(remapped, owner);
+            }
+        }
+        // Roughly a third of the keys belonged to u2
+        assert!((20..50).contains(&moved), "moved {}", moved);
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+use crate::metrics::Metrics;
+
+/// State of a downstream's circuit
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests are rejected without contacting the downstream
+    Open,
+    /// A single trial request is let through to probe recovery
+    HalfOpen,
+}
+
+/// Result of a request, as seen by the breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Downstream answered with a non-5xx status
+    Success,
+    /// Downstream answered with a 5xx status
+    ServerError,
+    /// No response was received (connection error or timeout)
+    RequestFailed,
+}
+
+/// Per-downstream circuit breaker
+///
+/// A circuit opens after `failure_threshold` consecutive failed requests or
+/// once the share of 5xx/failed results among the last `window` requests
+/// reaches `error_rate`. After `open_duration` one trial request is let
+/// through: success closes the circuit, failure opens it again.
+pub struct CircuitBreaker {
+    /// Consecutive failed requests that open a circuit (0 disables)
+    failure_threshold: u32,
+    /// Share of failures within the window that opens a circuit (0 disables)
+    error_rate: f64,
+    /// Number of recent results considered for `error_rate`
+    window: usize,
+    /// How long a circuit stays open before a trial request
+    open_duration: Duration,
+    circuits: Mutex<HashMap<String, Circuit>>,
+    metrics: Arc<Metrics>,
+}
+
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+    /// Recent results, `true` for failures
+    outcomes: VecDeque<bool>,
+    opened_at: Instant,
+    trial_in_flight: bool,
+}
+
+impl Circuit {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            outcomes: VecDeque::new(),
+            opened_at: Instant::now(),
+            trial_in_flight: false,
+        }
+    }
+}
+
+/// Permission to send a request to a downstream
+///
+/// The result should be reported with [`Permit::record`]. Dropping an
+/// unrecorded trial permit lets another trial request through.
+pub struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    /// `None` when the breaker is disabled or the result was recorded
+    downstream: Option<String>,
+    trial: bool,
+}
+
+impl Permit<'_> {
+    /// Report the result of the request
+    pub fn record(mut self, outcome: Outcome) {
+        if let Some(downstream) = self.downstream.take() {
+            self.breaker.record(&downstream, outcome, self.trial);
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if let Some(downstream) = self.downstream.take()
+            && self.trial
+            && let Some(circuit) = self.breaker.circuits.lock().unwrap().get_mut(&downstream)
+        {
+            circuit.trial_in_flight = false;
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Create a circuit breaker opening after `failure_threshold` consecutive
+    /// failed requests (0 disables)
+    pub fn new(failure_threshold: u32) -> Self {
+        Self {
+            failure_threshold,
+            error_rate: 0.0,
+            window: 0,
+            open_duration: Duration::from_secs(30),
+            circuits: Mutex::new(HashMap::new()),
+            metrics: Arc::default(),
+        }
+    }
+
+    /// Also open a circuit once the share of 5xx/failed results among the
+    /// last `window` requests reaches `error_rate` (0 disables)
+    pub fn with_error_rate(mut self, error_rate: f64, window: usize) -> Self {
+        self.error_rate = error_rate;
+        self.window = window;
+        self
+    }
+
+    /// Set how long a circuit stays open before a trial request
+    pub fn with_open_duration(mut self, open_duration: Duration) -> Self {
+        self.open_duration = open_duration;
+        self
+    }
+
+    /// Set the metrics registry used to record state changes
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.failure_threshold > 0 || (self.error_rate > 0.0 && self.window > 0)
+    }
+
+    /// Ask to send a request to `downstream`
+    ///
+    /// Returns `None` while the circuit is open.
+    pub fn acquire(&self, downstream: &str) -> Option<Permit<'_>> {
+        if !self.is_enabled() {
+            return Some(Permit {
+                breaker: self,
+                downstream: None,
+                trial: false,
+            });
+        }
+
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits
+            .entry(downstream.to_string())
+            .or_insert_with(Circuit::new);
+
+        let trial = match circuit.state {
+            CircuitState::Closed => false,
+            CircuitState::Open if circuit.opened_at.elapsed() >= self.open_duration => {
+                self.transition(downstream, circuit, CircuitState::HalfOpen);
+                circuit.trial_in_flight = true;
+                true
+            }
+            CircuitState::HalfOpen if !circuit.trial_in_flight => {
+                circuit.trial_in_flight = true;
+                true
+            }
+            CircuitState::Open | CircuitState::HalfOpen => {
+                self.metrics.record_circuit_rejection();
+                return None;
+            }
+        };
+
+        Some(Permit {
+            breaker: self,
+            downstream: Some(downstream.to_string()),
+            trial,
+        })
+    }
+
+    fn record(&self, downstream: &str, outcome: Outcome, trial: bool) {
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(downstream) else {
+            return;
+        };
+        let failed = outcome != Outcome::Success;
+
+        match circuit.state {
+            CircuitState::HalfOpen if trial => {
+                circuit.trial_in_flight = false;
+                if failed {
+                    self.open(downstream, circuit, "trial request failed");
+                } else {
+                    self.transition(downstream, circuit, CircuitState::Closed);
+                }
+            }
+            CircuitState::Closed => {
+                if outcome == Outcome::RequestFailed {
+                    circuit.consecutive_failures += 1;
+                } else {
+                    circuit.consecutive_failures = 0;
+                }
+                if self.window > 0 {
+                    circuit.outcomes.push_back(failed);
+                    if circuit.outcomes.len() > self.window {
+                        circuit.outcomes.pop_front();
+                    }
+                }
+
+                let failures = circuit.outcomes.iter().filter(|f| **f).count();
+                if self.failure_threshold > 0
+                    && circuit.consecutive_failures >= self.failure_threshold
+                {
+                    self.open(downstream, circuit, "consecutive failures");
+                } else if self.error_rate > 0.0
+                    && self.window > 0
+                    && circuit.outcomes.len() == self.window
+                    && failures as f64 / self.window as f64 >= self.error_rate
+                {
+                    self.open(downstream, circuit, "error rate");
+                }
+            }
+            // Requests sent before the circuit opened
+            CircuitState::Open | CircuitState::HalfOpen => {}
+        }
+    }
+
+    fn open(&self, downstream: &str, circuit: &mut Circuit, reason: &str) {
+        warn!(
+            "Circuit breaker opened for {} ({}), rejecting requests for {:?}",
+            downstream, reason, self.open_duration
+        );
+        circuit.opened_at = Instant::now();
+        self.transition(downstream, circuit, CircuitState::Open);
+    }
+
+    fn transition(&self, downstream: &str, circuit: &mut Circuit, to: CircuitState) {
+        let from = circuit.state;
+        circuit.state = to;
+        circuit.consecutive_failures = 0;
+        circuit.outcomes.clear();
+        match to {
+            CircuitState::HalfOpen => info!("Circuit breaker half-open for {}", downstream),
+            CircuitState::Closed => info!("Circuit breaker closed for {}", downstream),
+            CircuitState::Open => {}
+        }
+        self.metrics.record_circuit_transition(from, to);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOWNSTREAM: &str = "http://localhost:8080";
+
+    fn send(breaker: &CircuitBreaker, outcome: Outcome) -> bool {
+        match breaker.acquire(DOWNSTREAM) {
+            Some(permit) => {
+                permit.record(outcome);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[test]
+    fn test_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3).with_open_duration(Duration::from_secs(60));
+        assert!(send(&breaker, Outcome::RequestFailed));
+        assert!(send(&breaker, Outcome::RequestFailed));
+        // A response resets the streak
+        assert!(send(&breaker, Outcome::ServerError));
+        assert!(send(&breaker, Outcome::RequestFailed));
+        assert!(send(&breaker, Outcome::RequestFailed));
+        assert!(send(&breaker, Outcome::RequestFailed));
+
+        assert!(!send(&breaker, Outcome::Success));
+        assert!(breaker.acquire("http://other:8080").is_some());
+    }
+
+    #[test]
+    fn test_error_rate() {
+        let breaker = CircuitBreaker::new(0).with_error_rate(0.5, 4);
+        assert!(send(&breaker, Outcome::ServerError));
+        assert!(send(&breaker, Outcome::Success));
+        assert!(send(&breaker, Outcome::Success));
+        assert!(send(&breaker, Outcome::Success));
+        assert!(send(&breaker, Outcome::ServerError));
+        assert!(breaker.acquire(DOWNSTREAM).is_some());
+        assert!(send(&breaker, Outcome::ServerError));
+        assert!(breaker.acquire(DOWNSTREAM).is_none());
+    }
+
+    #[test]
+    fn test_half_open() {
+        let breaker = CircuitBreaker::new(1).with_open_duration(Duration::ZERO);
+        assert!(send(&breaker, Outcome::RequestFailed));
+
+        // Only one trial request at a time
+        let trial = breaker.acquire(DOWNSTREAM).unwrap();
+        assert!(breaker.acquire(DOWNSTREAM).is_none());
+        trial.record(Outcome::ServerError);
+
+        // An abandoned trial lets another one through
+        let trial = breaker.acquire(DOWNSTREAM).unwrap();
+        drop(trial);
+        let trial = breaker.acquire(DOWNSTREAM).unwrap();
+        trial.record(Outcome::Success);
+
+        assert!(send(&breaker, Outcome::Success));
+        assert!(send(&breaker, Outcome::Success));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let breaker = CircuitBreaker::new(0);
+        for _ in 0..10 {
+            assert!(send(&breaker, Outcome::RequestFailed));
+        }
+        assert!(breaker.circuits.lock().unwrap().is_empty());
+    }
+}
@@ -1,33 +1,123 @@
-use futures_util::StreamExt;
+use axum::body::{Body, Bytes, HttpBody};
+use futures_util::{Stream, StreamExt, stream};
 use reqwest::{Client, Method, Request, Url};
-use std::time::Duration;
-use tracing::{error, info};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    metrics::{Metrics, SessionLabels},
+    models::SessionConfig,
+    proxy::{
+        circuit_breaker::{CircuitBreaker, Outcome},
+        retry::{RETRIES_HEADER, RETRY_OPT_IN_HEADER, RetryPolicy},
+    },
+};
 
 /// HTTP proxy client
 pub struct HttpProxy {
-    client: Client,
+    /// Clients keyed by connect timeout, which reqwest only sets per client
+    clients: Mutex<HashMap<Option<Duration>, Client>>,
+    /// Default time to establish the downstream connection
+    connect_timeout: Option<Duration>,
+    /// Default time until downstream response headers are received
+    first_byte_timeout: Option<Duration>,
+    /// Default maximum gap between response body chunks
+    idle_timeout: Option<Duration>,
+    /// Maximum request body size in bytes (`None` means unlimited)
+    max_body_size: Option<u64>,
+    /// Latency and traffic metrics
+    metrics: Arc<Metrics>,
+    /// Per-downstream circuit breaker
+    breaker: CircuitBreaker,
+    /// Retry policy for failed downstream requests
+    retry: RetryPolicy,
 }
 
 impl HttpProxy {
-    /// Create a new HTTP proxy client
-    pub fn new(timeout: Duration) -> Self {
-        let client = Client::builder()
-            .timeout(timeout)
-            .build()
-            .expect("Failed to create HTTP client");
-
-        Self { client }
+    /// Create a new HTTP proxy client waiting up to `first_byte_timeout` for
+    /// response headers (0 disables)
+    pub fn new(first_byte_timeout: Duration) -> Self {
+        Self {
+            clients: Mutex::new(HashMap::new()),
+            connect_timeout: Some(Duration::from_secs(10)),
+            first_byte_timeout: non_zero(first_byte_timeout),
+            idle_timeout: Some(Duration::from_secs(60)),
+            max_body_size: None,
+            metrics: Arc::default(),
+            breaker: CircuitBreaker::new(0),
+            retry: RetryPolicy::new(1),
+        }
+    }
+
+    /// Set the downstream connect timeout (0 disables)
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = non_zero(timeout);
+        self
+    }
+
+    /// Set the maximum gap between response body chunks (0 disables)
+    ///
+    /// Unlike a total timeout, this lets long streaming responses such as SSE
+    /// run indefinitely as long as data keeps flowing.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = non_zero(timeout);
+        self
+    }
+
+    /// Set maximum request body size in bytes (0 means unlimited)
+    pub fn with_max_body_size(mut self, max_body_size: u64) -> Self {
+        self.max_body_size = (max_body_size > 0).then_some(max_body_size);
+        self
+    }
+
+    /// Set the metrics registry used to record latency and traffic
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Set the circuit breaker guarding downstreams
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// Set the retry policy for failed downstream requests
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
     }
 
     /// Forward HTTP request to downstream server
+    #[allow(clippy::too_many_arguments)]
     pub async fn forward_request(
         &self,
         downstream_url: &str,
         path: &str,
         method: Method,
         headers: axum::http::HeaderMap,
-        body: axum::body::Bytes,
+        body: Body,
+        labels: SessionLabels,
+        config: &SessionConfig,
     ) -> Result<axum::response::Response, ProxyError> {
+        let started = Instant::now();
+        let timeouts = config.timeouts;
+        let client = self.client(resolve(timeouts.connect, self.connect_timeout));
+        let first_byte_timeout = resolve(timeouts.first_byte, self.first_byte_timeout);
+        let idle_timeout = resolve(timeouts.idle, self.idle_timeout);
+        let max_body_size = match config.max_body_size {
+            Some(max_body_size) => (max_body_size > 0).then_some(max_body_size),
+            None => self.max_body_size,
+        };
+
         // Construct full downstream URL
         let full_url = format!("{}{}", downstream_url.trim_end_matches('/'), path);
         info!("Forwarding request to: {} {}", method, full_url);
@@ -37,39 +127,124 @@ impl HttpProxy {
             ProxyError::InvalidUrl(full_url.clone())
         })?;
 
-        // Build request
-        let mut request = Request::new(method.clone(), url);
+        // Reject oversized bodies up front when the client declares their length
+        if let (Some(limit), Some(length)) = (max_body_size, body.size_hint().exact())
+            && length > limit
+        {
+            warn!("Request body too large: {} > {} bytes", length, limit);
+            return Err(ProxyError::BodyTooLarge(limit));
+        }
 
-        // Copy request headers (filter out headers that shouldn't be forwarded)
-        let request_headers = request.headers_mut();
-        for (key, value) in headers.iter() {
-            let key_str = key.as_str();
-            // Skip headers that shouldn't be forwarded
-            if !matches!(
-                key_str,
-                "host" | "connection" | "transfer-encoding" | "content-length"
-            ) && let Ok(name) = reqwest::header::HeaderName::from_bytes(key.as_str().as_bytes())
-                && let Ok(val) = reqwest::header::HeaderValue::from_bytes(value.as_bytes())
+        // Buffer small bodies of retryable requests so they can be replayed
+        let retryable = self.retry.allows(&method, &headers);
+        let (replay_body, mut body) = match body.size_hint().exact() {
+            Some(length) if retryable && length <= self.retry.max_body_size() => {
+                let bytes = axum::body::to_bytes(body, length as usize)
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to read request body: {}", e);
+                        ProxyError::RequestFailed(e.to_string())
+                    })?;
+                self.metrics
+                    .add_http_request_bytes(&labels, bytes.len() as u64);
+                (Some(bytes), None)
+            }
+            _ => (None, Some(body)),
+        };
+        let max_attempts = if replay_body.is_some() {
+            self.retry.max_attempts()
+        } else {
+            1
+        };
+
+        let body_too_large = Arc::new(AtomicBool::new(false));
+        let mut attempt = 1;
+        let response = loop {
+            // Fail fast while the downstream's circuit is open
+            let permit = self.breaker.acquire(downstream_url).ok_or_else(|| {
+                warn!("Circuit open, rejecting request to: {}", downstream_url);
+                ProxyError::CircuitOpen(downstream_url.to_string())
+            })?;
+
+            let mut request = self.build_request(&method, &url, &headers, &config.headers);
+            if let Some(bytes) = &replay_body {
+                if !bytes.is_empty() {
+                    *request.body_mut() = Some(bytes.clone().into());
+                }
+            } else if let Some(body) = body.take()
+                && !body.is_end_stream()
             {
-                request_headers.insert(name, val);
+                // Stream request body to downstream without buffering it
+                *request.body_mut() = Some(self.stream_body(
+                    body,
+                    max_body_size,
+                    body_too_large.clone(),
+                    labels.clone(),
+                ));
             }
-        }
 
-        // Set request body
-        if !body.is_empty() {
-            *request.body_mut() = Some(body.to_vec().into());
-        }
+            // Send request, waiting at most `first_byte_timeout` for response headers
+            let sent = match first_byte_timeout {
+                Some(limit) => tokio::time::timeout(limit, client.execute(request)).await,
+                None => Ok(client.execute(request).await),
+            };
+            let reason = match sent {
+                Ok(Ok(response)) => {
+                    let status = response.status();
+                    permit.record(if status.is_server_error() {
+                        Outcome::ServerError
+                    } else {
+                        Outcome::Success
+                    });
+                    if attempt >= max_attempts || !self.retry.retries_status(status) {
+                        break response;
+                    }
+                    status.to_string()
+                }
+                Ok(Err(_)) if body_too_large.load(Ordering::Relaxed) => {
+                    let limit = max_body_size.unwrap_or_default();
+                    warn!("Request body exceeded {} bytes while streaming", limit);
+                    return Err(ProxyError::BodyTooLarge(limit));
+                }
+                Ok(Err(e)) => {
+                    error!("Failed to request downstream server: {}", e);
+                    permit.record(Outcome::RequestFailed);
+                    if e.is_timeout() {
+                        return Err(ProxyError::Timeout(e.to_string()));
+                    }
+                    if attempt >= max_attempts || !self.retry.retries_error(&e) {
+                        return Err(ProxyError::RequestFailed(e.to_string()));
+                    }
+                    e.to_string()
+                }
+                Err(_) => {
+                    let limit = first_byte_timeout.unwrap_or_default();
+                    error!(
+                        "Downstream did not respond within {:?}: {}",
+                        limit, full_url
+                    );
+                    permit.record(Outcome::RequestFailed);
+                    return Err(ProxyError::Timeout(format!(
+                        "no response headers within {:?}",
+                        limit
+                    )));
+                }
+            };
 
-        // Send request
-        let response = self.client.execute(request).await.map_err(|e| {
-            error!("Failed to request downstream server: {}", e);
-            ProxyError::RequestFailed(e.to_string())
-        })?;
+            let delay = self.retry.backoff(attempt);
+            attempt += 1;
+            warn!(
+                "Retrying {} {} in {:?} (attempt {}/{}): {}",
+                method, full_url, delay, attempt, max_attempts, reason
+            );
+            tokio::time::sleep(delay).await;
+        };
 
         info!(
             "Received response from downstream server: {}",
             response.status()
         );
+        self.metrics.observe_http_ttfb(&labels, started.elapsed());
 
         // Build response
         let mut builder = axum::http::Response::builder().status(response.status());
@@ -78,15 +253,30 @@ impl HttpProxy {
         for (key, value) in response.headers().iter() {
             builder = builder.header(key, value);
         }
+        if attempt > 1 {
+            builder = builder.header(RETRIES_HEADER, attempt - 1);
+        }
 
-        // Convert reqwest response stream to axum body for streaming support
-        let stream = response.bytes_stream();
-        let body_stream = stream.map(|result| {
+        // Convert reqwest response stream to axum body for streaming support,
+        // recording the total duration once the body is finished or dropped
+        let timer = ResponseTimer {
+            metrics: self.metrics.clone(),
+            labels,
+            started,
+        };
+        let stream = response.bytes_stream().map(|result| {
             result.map_err(|e| {
                 error!("Error reading response stream: {}", e);
-                std::io::Error::other(e)
+                io::Error::other(e)
             })
         });
+        let body_stream = with_idle_timeout(stream, idle_timeout).map(move |result| {
+            let chunk = result?;
+            timer
+                .metrics
+                .add_http_response_bytes(&timer.labels, chunk.len() as u64);
+            Ok::<_, std::io::Error>(chunk)
+        });
 
         // Build final response with streaming body
         let final_response = builder
@@ -98,6 +288,139 @@ impl HttpProxy {
 
         Ok(final_response)
     }
+
+    /// Get the client for a connect timeout, creating it on first use
+    fn client(&self, connect_timeout: Option<Duration>) -> Client {
+        self.clients
+            .lock()
+            .unwrap()
+            .entry(connect_timeout)
+            .or_insert_with(|| {
+                let mut builder = Client::builder();
+                if let Some(timeout) = connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                builder.build().expect("Failed to create HTTP client")
+            })
+            .clone()
+    }
+
+    /// Build a downstream request carrying the client's headers plus the
+    /// session's extra headers
+    fn build_request(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &axum::http::HeaderMap,
+        extra_headers: &BTreeMap<String, String>,
+    ) -> Request {
+        let mut request = Request::new(method.clone(), url.clone());
+
+        // Copy request headers (filter out headers that shouldn't be forwarded)
+        let request_headers = request.headers_mut();
+        for (key, value) in headers.iter() {
+            let key_str = key.as_str();
+            // Skip headers that shouldn't be forwarded
+            if !matches!(
+                key_str,
+                "host" | "connection" | "transfer-encoding" | RETRY_OPT_IN_HEADER
+            ) && let Ok(name) = reqwest::header::HeaderName::from_bytes(key.as_str().as_bytes())
+                && let Ok(val) = reqwest::header::HeaderValue::from_bytes(value.as_bytes())
+            {
+                request_headers.insert(name, val);
+            }
+        }
+        for (key, value) in extra_headers {
+            match (
+                reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+                reqwest::header::HeaderValue::from_str(value),
+            ) {
+                (Ok(name), Ok(val)) => {
+                    request_headers.insert(name, val);
+                }
+                _ => warn!("Skipping invalid session header: {}", key),
+            }
+        }
+
+        request
+    }
+
+    /// Convert the inbound body into a streaming reqwest body, aborting the
+    /// stream (and setting `too_large`) once `limit` is exceeded
+    fn stream_body(
+        &self,
+        body: Body,
+        limit: Option<u64>,
+        too_large: Arc<AtomicBool>,
+        labels: SessionLabels,
+    ) -> reqwest::Body {
+        let metrics = self.metrics.clone();
+        let mut received: u64 = 0;
+
+        let stream = body.into_data_stream().map(move |chunk| {
+            let chunk = chunk.map_err(std::io::Error::other)?;
+            received += chunk.len() as u64;
+            if let Some(limit) = limit
+                && received > limit
+            {
+                too_large.store(true, Ordering::Relaxed);
+                return Err(std::io::Error::other("request body too large"));
+            }
+            metrics.add_http_request_bytes(&labels, chunk.len() as u64);
+            Ok(chunk)
+        });
+
+        reqwest::Body::wrap_stream(stream)
+    }
+}
+
+/// End a response stream with a `TimedOut` error once no chunk arrives
+/// within `idle`
+fn with_idle_timeout<S>(stream: S, idle: Option<Duration>) -> impl Stream<Item = io::Result<Bytes>>
+where
+    S: Stream<Item = io::Result<Bytes>> + Send + 'static,
+{
+    stream::unfold(Some(Box::pin(stream)), move |state| async move {
+        let mut inner = state?;
+        let next = match idle {
+            Some(limit) => match tokio::time::timeout(limit, inner.next()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    error!("Downstream response idle for {:?}, aborting stream", limit);
+                    let error = io::Error::new(io::ErrorKind::TimedOut, "response idle timeout");
+                    return Some((Err(error), None));
+                }
+            },
+            None => inner.next().await,
+        };
+        next.map(|item| (item, Some(inner)))
+    })
+}
+
+/// Apply a per-session timeout override in seconds (0 disables the timeout)
+fn resolve(seconds: Option<u64>, default: Option<Duration>) -> Option<Duration> {
+    match seconds {
+        Some(seconds) => non_zero(Duration::from_secs(seconds)),
+        None => default,
+    }
+}
+
+fn non_zero(timeout: Duration) -> Option<Duration> {
+    (!timeout.is_zero()).then_some(timeout)
+}
+
+/// Records the total request duration when the response body is dropped
+struct ResponseTimer {
+    metrics: Arc<Metrics>,
+    labels: SessionLabels,
+    started: Instant,
+}
+
+impl Drop for ResponseTimer {
+    fn drop(&mut self) {
+        self.metrics
+            .observe_http_duration(&self.labels, self.started.elapsed());
+    }
 }
 
 /// Proxy error types
@@ -111,4 +434,13 @@ pub enum ProxyError {
 
     #[error("Failed to build response: {0}")]
     ResponseBuildFailed(String),
+
+    #[error("Request body exceeds limit of {0} bytes")]
+    BodyTooLarge(u64),
+
+    #[error("Circuit breaker open for {0}")]
+    CircuitOpen(String),
+
+    #[error("Downstream timed out: {0}")]
+    Timeout(String),
 }
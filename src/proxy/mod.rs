@@ -1,5 +1,13 @@
+pub mod balancer;
+pub mod circuit_breaker;
 pub mod http_proxy;
+pub mod rate_limit;
+pub mod retry;
 pub mod ws_proxy;
 
+pub use balancer::Balancer;
+pub use circuit_breaker::CircuitBreaker;
 pub use http_proxy::HttpProxy;
-pub use ws_proxy::WsProxy;
+pub use rate_limit::RateLimiter;
+pub use retry::RetryPolicy;
+pub use ws_proxy::{DownstreamWs, WsProxy};
@@ -0,0 +1,90 @@
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+use crate::models::RateLimit;
+
+/// Per-session token buckets for sessions with a `rate_limit` override
+///
+/// Limits are read from the session on every call, so a changed
+/// configuration applies as soon as the session cache picks it up.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Create an empty rate limiter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take one token from the session's bucket
+    ///
+    /// Returns `false` when the session has exhausted its limit.
+    pub fn check(&self, session_id: &str, limit: &RateLimit) -> bool {
+        self.check_at(session_id, limit, Instant::now())
+    }
+
+    fn check_at(&self, session_id: &str, limit: &RateLimit, now: Instant) -> bool {
+        let capacity = limit.capacity();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(session_id.to_string())
+            .or_insert_with(|| Bucket {
+                tokens: capacity,
+                refilled_at: now,
+            });
+
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens
+            + elapsed.as_secs_f64() * limit.requests_per_second.max(0.0))
+        .min(capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_burst_and_refill() {
+        let limiter = RateLimiter::new();
+        let limit = RateLimit {
+            requests_per_second: 2.0,
+            burst: Some(3),
+        };
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", &limit, start));
+        }
+        assert!(!limiter.check_at("a", &limit, start));
+        // Other sessions have their own bucket
+        assert!(limiter.check_at("b", &limit, start));
+
+        // Half a second refills one token
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.check_at("a", &limit, later));
+        assert!(!limiter.check_at("a", &limit, later));
+
+        // Refill never exceeds the burst size
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", &limit, much_later));
+        }
+        assert!(!limiter.check_at("a", &limit, much_later));
+    }
+}
@@ -0,0 +1,158 @@
+use axum::http::{HeaderMap, Method, StatusCode};
+use rand::Rng;
+use std::time::Duration;
+
+/// Request header that opts a non-idempotent request into retries
+pub const RETRY_OPT_IN_HEADER: &str = "x-ss-proxy-retry";
+/// Response header reporting how many retries were needed
+pub const RETRIES_HEADER: &str = "x-ss-proxy-retries";
+
+/// Upper bound for a single backoff delay
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// When and how often failed downstream requests are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts per request, including the first (1 disables retries)
+    max_attempts: u32,
+    /// Base delay, doubled after each retry
+    backoff: Duration,
+    /// Downstream response statuses that trigger a retry
+    statuses: Vec<StatusCode>,
+    /// Largest request body buffered so it can be replayed
+    max_body_size: u64,
+}
+
+impl RetryPolicy {
+    /// Create a retry policy making up to `max_attempts` attempts per request
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            backoff: Duration::from_millis(100),
+            statuses: vec![
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            max_body_size: 1024 * 1024,
+        }
+    }
+
+    /// Set the base backoff delay
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the downstream response statuses that trigger a retry
+    ///
+    /// Invalid status codes are ignored.
+    pub fn with_statuses(mut self, statuses: &[u16]) -> Self {
+        self.statuses = statuses
+            .iter()
+            .filter_map(|s| StatusCode::from_u16(*s).ok())
+            .collect();
+        self
+    }
+
+    /// Set the largest request body buffered for replay
+    pub fn with_max_body_size(mut self, max_body_size: u64) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Total attempts per request, including the first
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Largest request body buffered for replay
+    pub fn max_body_size(&self) -> u64 {
+        self.max_body_size
+    }
+
+    /// Whether a request may be retried at all
+    ///
+    /// Only idempotent methods are retried unless the client sends
+    /// `x-ss-proxy-retry: true`.
+    pub fn allows(&self, method: &Method, headers: &HeaderMap) -> bool {
+        if self.max_attempts <= 1 {
+            return false;
+        }
+        let opted_in = headers
+            .get(RETRY_OPT_IN_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| matches!(v.trim(), "true" | "1"));
+        opted_in || is_idempotent(method)
+    }
+
+    /// Whether a downstream response status should be retried
+    pub fn retries_status(&self, status: StatusCode) -> bool {
+        self.statuses.contains(&status)
+    }
+
+    /// Whether a transport error should be retried
+    ///
+    /// Timeouts are not retried, since each attempt already waited the full
+    /// request timeout.
+    pub fn retries_error(&self, error: &reqwest::Error) -> bool {
+        !error.is_timeout()
+    }
+
+    /// Delay before the given retry (1-based): exponential backoff with jitter
+    /// in the upper half of the interval
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .backoff
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(MAX_BACKOFF);
+        delay.mul_f64(rand::rng().random_range(0.5..=1.0))
+    }
+}
+
+/// Methods that can be repeated without changing the result (RFC 9110)
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_allows() {
+        let policy = RetryPolicy::new(3);
+        let mut headers = HeaderMap::new();
+        assert!(policy.allows(&Method::GET, &headers));
+        assert!(policy.allows(&Method::PUT, &headers));
+        assert!(!policy.allows(&Method::POST, &headers));
+
+        headers.insert(RETRY_OPT_IN_HEADER, HeaderValue::from_static("true"));
+        assert!(policy.allows(&Method::POST, &headers));
+
+        assert!(!RetryPolicy::new(1).allows(&Method::GET, &headers));
+    }
+
+    #[test]
+    fn test_statuses() {
+        let policy = RetryPolicy::new(2).with_statuses(&[503, 1000]);
+        assert!(policy.retries_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!policy.retries_status(StatusCode::BAD_GATEWAY));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new(5).with_backoff(Duration::from_millis(100));
+        for _ in 0..20 {
+            let first = policy.backoff(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = policy.backoff(3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+        }
+        assert!(policy.backoff(30) <= MAX_BACKOFF);
+    }
+}
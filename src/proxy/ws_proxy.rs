@@ -1,42 +1,365 @@
-use axum::extract::ws::{Message, WebSocket};
+use axum::{
+    extract::ws::{CloseFrame, Message, WebSocket, close_code},
+    http::{HeaderMap, HeaderValue, header},
+};
 use futures_util::{SinkExt, StreamExt};
-use tokio_tungstenite::{connect_async, tungstenite::Message as TungsteniteMessage};
-use tracing::{error, info, warn};
+use std::{
+    collections::HashMap,
+    pin::pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+use tokio::{net::TcpStream, time::timeout};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, connect_async_with_config,
+    tungstenite::{
+        Error as TungsteniteError, Message as TungsteniteMessage,
+        client::IntoClientRequest,
+        error::{ProtocolError, SubProtocolError},
+        http::HeaderName,
+        protocol::{
+            CloseFrame as TungsteniteCloseFrame, WebSocketConfig, frame::coding::CloseCode,
+        },
+    },
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::WsPathMode,
+    metrics::{Metrics, SessionLabels, WsDirection},
+    models::SessionConfig,
+    shutdown::Shutdown,
+};
+
+/// Handshake headers generated by the WebSocket client itself, never forwarded
+const RESERVED_HANDSHAKE_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "upgrade",
+    "content-length",
+    "transfer-encoding",
+    "sec-websocket-key",
+    "sec-websocket-version",
+    "sec-websocket-extensions",
+    "sec-websocket-accept",
+];
+
+/// Close code sent to the client when the downstream connection fails (RFC 6455 "Bad Gateway")
+const CLOSE_BAD_GATEWAY: u16 = 1014;
+
+/// Close reason sent to both peers when the proxy shuts down
+const SHUTDOWN_REASON: &str = "Server shutting down";
+
+/// Time allowed for the peer to answer a relayed close frame
+const CLOSE_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Established downstream WebSocket connection
+pub struct DownstreamWs {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    /// Subprotocol selected by the downstream server
+    pub protocol: Option<String>,
+}
 
 /// WebSocket proxy
-pub struct WsProxy;
+pub struct WsProxy {
+    /// Client handshake headers forwarded downstream (lowercase names, `*` suffix for prefixes)
+    forward_headers: Vec<String>,
+    /// How the `/ws/{session_id}` prefix maps onto the downstream path
+    path_mode: WsPathMode,
+    /// Replacement prefix for `WsPathMode::Replace`
+    path_prefix: String,
+    /// Shutdown coordinator used to close and track active connections
+    shutdown: Shutdown,
+    /// Connection and message metrics
+    metrics: Arc<Metrics>,
+    /// Open connections per session, for `websocket.max_connections`
+    connections: Arc<Mutex<HashMap<String, u32>>>,
+}
+
+/// Reserved connection slot, released when dropped
+pub struct ConnectionSlot {
+    connections: Arc<Mutex<HashMap<String, u32>>>,
+    session_id: String,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut connections = self.connections.lock().unwrap();
+        if let Some(open) = connections.get_mut(&self.session_id) {
+            *open -= 1;
+            if *open == 0 {
+                connections.remove(&self.session_id);
+            }
+        }
+    }
+}
 
 impl WsProxy {
-    /// Handle WebSocket connection, forwarding messages between client and downstream server
-    pub async fn handle_connection(
-        client_ws: WebSocket,
+    /// Create a new WebSocket proxy
+    pub fn new() -> Self {
+        Self {
+            forward_headers: Vec::new(),
+            path_mode: WsPathMode::Keep,
+            path_prefix: String::new(),
+            shutdown: Shutdown::new(),
+            metrics: Arc::default(),
+            connections: Arc::default(),
+        }
+    }
+
+    /// Set the shutdown coordinator used to close and track active connections
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Set the metrics registry used to record connections and messages
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Check whether the server is shutting down and no longer accepts connections
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_triggered()
+    }
+
+    /// Set how the `/ws/{session_id}` prefix maps onto the downstream path
+    pub fn with_path_mode(mut self, mode: WsPathMode, prefix: impl Into<String>) -> Self {
+        self.path_mode = mode;
+        self.path_prefix = prefix.into().trim_end_matches('/').to_string();
+        if !self.path_prefix.is_empty() && !self.path_prefix.starts_with('/') {
+            self.path_prefix.insert(0, '/');
+        }
+        self
+    }
+
+    /// Map the client request path (`/ws/{session_id}/...`) onto the downstream path
+    pub fn downstream_path(&self, session_id: &str, full_path: &str) -> String {
+        let rest = full_path
+            .strip_prefix("/ws/")
+            .and_then(|p| p.strip_prefix(session_id))
+            .unwrap_or("");
+
+        let path = match self.path_mode {
+            WsPathMode::Keep => return full_path.to_string(),
+            WsPathMode::Strip => rest.to_string(),
+            WsPathMode::Replace => format!("{}{}", self.path_prefix, rest),
+        };
+
+        if path.is_empty() {
+            "/".to_string()
+        } else {
+            path
+        }
+    }
+
+    /// Set client handshake headers forwarded downstream
+    ///
+    /// Entries are case-insensitive header names; a trailing `*` matches any
+    /// header with that prefix (e.g. `x-*`).
+    pub fn with_forward_headers<I, S>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.forward_headers = headers
+            .into_iter()
+            .map(|h| h.as_ref().trim().to_ascii_lowercase())
+            .filter(|h| !h.is_empty())
+            .collect();
+        self
+    }
+
+    /// Select the client handshake headers that should reach the downstream server
+    pub fn select_headers(&self, headers: &HeaderMap) -> HeaderMap {
+        let mut selected = HeaderMap::new();
+        for (name, value) in headers.iter() {
+            let name_str = name.as_str();
+            if RESERVED_HANDSHAKE_HEADERS.contains(&name_str) {
+                continue;
+            }
+            let allowed =
+                self.forward_headers
+                    .iter()
+                    .any(|pattern| match pattern.strip_suffix('*') {
+                        Some(prefix) => name_str.starts_with(prefix),
+                        None => name_str == pattern,
+                    });
+            if allowed {
+                selected.append(name.clone(), value.clone());
+            }
+        }
+        selected
+    }
+
+    /// Reserve a connection slot for a session
+    ///
+    /// Returns `None` once `max` connections of the session are open.
+    pub fn reserve_connection(&self, session_id: &str, max: Option<u32>) -> Option<ConnectionSlot> {
+        let mut connections = self.connections.lock().unwrap();
+        let open = connections.get(session_id).copied().unwrap_or(0);
+        if max.is_some_and(|max| open >= max) {
+            return None;
+        }
+        connections.insert(session_id.to_string(), open + 1);
+        Some(ConnectionSlot {
+            connections: self.connections.clone(),
+            session_id: session_id.to_string(),
+        })
+    }
+
+    /// Parse the subprotocols offered in the client's `Sec-WebSocket-Protocol` headers
+    pub fn offered_protocols(headers: &HeaderMap) -> Vec<String> {
+        headers
+            .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+
+    /// Connect to the downstream WebSocket server, offering the client's subprotocols
+    ///
+    /// The session's extra headers, connect timeout and message size limit
+    /// are applied to the downstream connection.
+    pub async fn connect(
+        &self,
         downstream_url: &str,
-    ) -> Result<(), WsProxyError> {
+        headers: HeaderMap,
+        protocols: &[String],
+        config: &SessionConfig,
+    ) -> Result<DownstreamWs, WsProxyError> {
         info!(
             "Establishing connection to downstream WebSocket: {}",
             downstream_url
         );
 
-        // Connect to downstream WebSocket server
-        let (downstream_ws, _) = connect_async(downstream_url).await.map_err(|e| {
-            error!("Failed to connect to downstream WebSocket: {}", e);
+        // Build handshake request carrying the forwarded client headers
+        let mut request = downstream_url.into_client_request().map_err(|e| {
+            error!("Invalid downstream WebSocket URL: {}", e);
             WsProxyError::ConnectionFailed(e.to_string())
         })?;
+        debug!(
+            "Forwarding handshake headers: {:?}",
+            headers.keys().collect::<Vec<_>>()
+        );
+        for (name, value) in headers.iter() {
+            request.headers_mut().append(name.clone(), value.clone());
+        }
+        for (name, value) in &config.headers {
+            match (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                (Ok(name), Ok(value)) if !RESERVED_HANDSHAKE_HEADERS.contains(&name.as_str()) => {
+                    request.headers_mut().insert(name, value);
+                }
+                _ => warn!("Skipping invalid session header: {}", name),
+            }
+        }
+        if !protocols.is_empty() {
+            let offered = HeaderValue::from_str(&protocols.join(", ")).map_err(|e| {
+                WsProxyError::ConnectionFailed(format!("Invalid subprotocol list: {}", e))
+            })?;
+            request
+                .headers_mut()
+                .insert(header::SEC_WEBSOCKET_PROTOCOL, offered);
+        }
+
+        // Connect to downstream WebSocket server, bounded by the session's connect timeout
+        let ws_config = config.websocket.max_message_size.map(|size| {
+            WebSocketConfig::default()
+                .max_message_size(Some(size))
+                .max_frame_size(Some(size))
+        });
+        let connecting = connect_async_with_config(request, ws_config, false);
+        let connected = match config.timeouts.connect.filter(|secs| *secs > 0) {
+            Some(secs) => timeout(Duration::from_secs(secs), connecting)
+                .await
+                .map_err(|_| {
+                    error!("Downstream WebSocket did not connect within {}s", secs);
+                    WsProxyError::ConnectionFailed(format!("connect timed out after {}s", secs))
+                })?,
+            None => connecting.await,
+        };
+
+        let (stream, response) = connected.map_err(|e| match e {
+            // Client offered subprotocols but the downstream accepted none of them
+            TungsteniteError::Protocol(ProtocolError::SecWebSocketSubProtocolError(
+                e @ SubProtocolError::NoSubProtocol,
+            )) => {
+                warn!("Downstream WebSocket rejected offered subprotocols: {}", e);
+                WsProxyError::SubprotocolRejected(e.to_string())
+            }
+            e => {
+                error!("Failed to connect to downstream WebSocket: {}", e);
+                WsProxyError::ConnectionFailed(e.to_string())
+            }
+        })?;
+
+        let protocol = response
+            .headers()
+            .get(header::SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string());
 
-        info!("Successfully connected to downstream WebSocket");
+        info!(
+            "Successfully connected to downstream WebSocket (subprotocol: {:?})",
+            protocol
+        );
+
+        Ok(DownstreamWs { stream, protocol })
+    }
+
+    /// Handle WebSocket connection, forwarding messages between client and downstream server
+    pub async fn handle_connection(
+        &self,
+        client_ws: WebSocket,
+        downstream: DownstreamWs,
+        labels: SessionLabels,
+    ) -> Result<(), WsProxyError> {
+        let _guard = self.shutdown.track();
+        let _active = self.metrics.track_ws_connection(&labels);
+        let downstream_ws = downstream.stream;
+        // Set when a client message exceeded the session's size limit
+        let client_too_big = AtomicBool::new(false);
 
         let (mut downstream_write, mut downstream_read) = downstream_ws.split();
         let (mut client_write, mut client_read) = client_ws.split();
 
         // Task 1: Client -> Downstream server
         let client_to_downstream = async {
-            while let Some(msg) = client_read.next().await {
+            let mut closing = false;
+            loop {
+                let msg = tokio::select! {
+                    msg = client_read.next() => msg,
+                    _ = self.shutdown.triggered(), if !closing => {
+                        info!("Server shutting down, closing downstream WebSocket");
+                        closing = true;
+                        let _ = downstream_write
+                            .send(TungsteniteMessage::Close(Some(TungsteniteCloseFrame {
+                                code: CloseCode::Away,
+                                reason: SHUTDOWN_REASON.into(),
+                            })))
+                            .await;
+                        continue;
+                    }
+                };
+                let Some(msg) = msg else { break };
                 match msg {
                     Ok(Message::Text(buffer)) => {
                         info!(
                             "Client -> Downstream: Text message ({} bytes)",
                             buffer.len()
                         );
+                        self.metrics
+                            .record_ws_message(&labels, WsDirection::ClientToDownstream);
                         if let Err(e) = downstream_write
                             .send(TungsteniteMessage::Text(buffer.to_string().into()))
                             .await
@@ -50,6 +373,8 @@ impl WsProxy {
                             "Client -> Downstream: Binary message ({} bytes)",
                             data.len()
                         );
+                        self.metrics
+                            .record_ws_message(&labels, WsDirection::ClientToDownstream);
                         if let Err(e) = downstream_write
                             .send(TungsteniteMessage::Binary(data))
                             .await
@@ -74,10 +399,26 @@ impl WsProxy {
                             break;
                         }
                     }
-                    Ok(Message::Close(_frame)) => {
-                        info!("Client closed connection");
-                        let _ = downstream_write.send(TungsteniteMessage::Close(None)).await;
-                        break;
+                    Ok(Message::Close(frame)) => {
+                        info!("Client closed connection: {}", describe_close(&frame));
+                        let _ = downstream_write
+                            .send(TungsteniteMessage::Close(frame.map(to_downstream_close)))
+                            .await;
+                        // Flush any queued close reply if the downstream closed first
+                        let _ = downstream_write.close().await;
+                        return;
+                    }
+                    Err(e) if is_capacity_error(&e) => {
+                        warn!("Client message exceeds session limit: {}", e);
+                        client_too_big.store(true, Ordering::Relaxed);
+                        let _ = downstream_write
+                            .send(TungsteniteMessage::Close(Some(TungsteniteCloseFrame {
+                                code: CloseCode::Size,
+                                reason: "Message too big".into(),
+                            })))
+                            .await;
+                        let _ = downstream_write.close().await;
+                        return;
                     }
                     Err(e) => {
                         warn!("Failed to receive message from client: {}", e);
@@ -85,14 +426,41 @@ impl WsProxy {
                     }
                 }
             }
+
+            // Client went away without a close handshake
+            let _ = downstream_write
+                .send(TungsteniteMessage::Close(Some(TungsteniteCloseFrame {
+                    code: CloseCode::Away,
+                    reason: "Client disconnected".into(),
+                })))
+                .await;
+            let _ = downstream_write.close().await;
         };
 
         // Task 2: Downstream server -> Client
         let downstream_to_client = async {
-            while let Some(msg) = downstream_read.next().await {
+            let mut closing = false;
+            loop {
+                let msg = tokio::select! {
+                    msg = downstream_read.next() => msg,
+                    _ = self.shutdown.triggered(), if !closing => {
+                        info!("Server shutting down, closing client WebSocket");
+                        closing = true;
+                        let _ = client_write
+                            .send(Message::Close(Some(CloseFrame {
+                                code: close_code::AWAY,
+                                reason: SHUTDOWN_REASON.into(),
+                            })))
+                            .await;
+                        continue;
+                    }
+                };
+                let Some(msg) = msg else { break };
                 match msg {
                     Ok(TungsteniteMessage::Text(text)) => {
                         info!("Downstream -> Client: Text message ({} bytes)", text.len());
+                        self.metrics
+                            .record_ws_message(&labels, WsDirection::DownstreamToClient);
                         if let Err(e) = client_write
                             .send(Message::Text(text.to_string().into()))
                             .await
@@ -106,6 +474,8 @@ impl WsProxy {
                             "Downstream -> Client: Binary message ({} bytes)",
                             data.len()
                         );
+                        self.metrics
+                            .record_ws_message(&labels, WsDirection::DownstreamToClient);
                         if let Err(e) = client_write.send(Message::Binary(data)).await {
                             error!("Failed to forward binary message to client: {}", e);
                             break;
@@ -125,29 +495,66 @@ impl WsProxy {
                             break;
                         }
                     }
-                    Ok(TungsteniteMessage::Close(_)) => {
-                        info!("Downstream server closed connection");
+                    Ok(TungsteniteMessage::Close(frame)) => {
+                        let frame = frame.map(to_client_close);
+                        info!(
+                            "Downstream server closed connection: {}",
+                            describe_close(&frame)
+                        );
+                        let _ = client_write.send(Message::Close(frame)).await;
+                        // Flush any queued close reply if the client closed first
                         let _ = client_write.close().await;
-                        break;
+                        return;
                     }
                     Ok(TungsteniteMessage::Frame(_)) => {
                         // Ignore raw frames
                     }
+                    Err(TungsteniteError::Capacity(e)) => {
+                        warn!("Downstream message exceeds session limit: {}", e);
+                        let _ = client_write
+                            .send(Message::Close(Some(CloseFrame {
+                                code: close_code::SIZE,
+                                reason: "Message too big".into(),
+                            })))
+                            .await;
+                        let _ = client_write.close().await;
+                        return;
+                    }
                     Err(e) => {
                         warn!("Failed to receive message from downstream: {}", e);
                         break;
                     }
                 }
             }
+
+            // Downstream failed mid-stream without a close handshake
+            let frame = if client_too_big.load(Ordering::Relaxed) {
+                CloseFrame {
+                    code: close_code::SIZE,
+                    reason: "Message too big".into(),
+                }
+            } else {
+                CloseFrame {
+                    code: CLOSE_BAD_GATEWAY,
+                    reason: "Downstream connection failed".into(),
+                }
+            };
+            let _ = client_write.send(Message::Close(Some(frame))).await;
+            let _ = client_write.close().await;
         };
 
-        // Run both tasks concurrently
+        // Run both tasks concurrently; once one side closes, give the other side
+        // a bounded amount of time to complete the close handshake
+        let mut client_to_downstream = pin!(client_to_downstream);
+        let mut downstream_to_client = pin!(downstream_to_client);
         tokio::select! {
-            _ = client_to_downstream => {
+            _ = &mut client_to_downstream => {
                 info!("Client to downstream forwarding task ended");
+                let _ = timeout(CLOSE_HANDSHAKE_TIMEOUT, downstream_to_client).await;
             }
-            _ = downstream_to_client => {
+            _ = &mut downstream_to_client => {
                 info!("Downstream to client forwarding task ended");
+                let _ = timeout(CLOSE_HANDSHAKE_TIMEOUT, client_to_downstream).await;
             }
         }
 
@@ -156,9 +563,148 @@ impl WsProxy {
     }
 }
 
+/// Convert a client close frame into a downstream close frame
+fn to_downstream_close(frame: CloseFrame) -> TungsteniteCloseFrame {
+    TungsteniteCloseFrame {
+        code: CloseCode::from(frame.code),
+        reason: frame.reason.as_str().into(),
+    }
+}
+
+/// Convert a downstream close frame into a client close frame
+fn to_client_close(frame: TungsteniteCloseFrame) -> CloseFrame {
+    CloseFrame {
+        code: frame.code.into(),
+        reason: frame.reason.as_str().into(),
+    }
+}
+
+/// Check whether a client read failed because a message exceeded the size limit
+fn is_capacity_error(error: &axum::Error) -> bool {
+    std::error::Error::source(error)
+        .and_then(|e| e.downcast_ref::<TungsteniteError>())
+        .is_some_and(|e| matches!(e, TungsteniteError::Capacity(_)))
+}
+
+/// Format a close frame for logging
+fn describe_close(frame: &Option<CloseFrame>) -> String {
+    match frame {
+        Some(frame) => format!("code={} reason={:?}", frame.code, frame.reason.as_str()),
+        None => "no close frame".to_string(),
+    }
+}
+
+impl Default for WsProxy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// WebSocket proxy error
 #[derive(Debug, thiserror::Error)]
 pub enum WsProxyError {
     #[error("Failed to connect to downstream WebSocket: {0}")]
     ConnectionFailed(String),
+
+    #[error("Downstream WebSocket rejected offered subprotocols: {0}")]
+    SubprotocolRejected(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_headers() {
+        let proxy = WsProxy::new().with_forward_headers(["Authorization", "cookie", "x-*"]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer abc".parse().unwrap());
+        headers.insert("cookie", "a=1".parse().unwrap());
+        headers.insert("x-tenant", "t1".parse().unwrap());
+        headers.insert("accept-language", "en".parse().unwrap());
+        headers.insert("sec-websocket-key", "dGhlIHNhbXBsZQ==".parse().unwrap());
+        headers.insert("host", "proxy.local".parse().unwrap());
+
+        let selected = proxy.select_headers(&headers);
+        assert_eq!(selected.len(), 3);
+        assert_eq!(selected["authorization"], "Bearer abc");
+        assert_eq!(selected["cookie"], "a=1");
+        assert_eq!(selected["x-tenant"], "t1");
+
+        let none = WsProxy::new().select_headers(&headers);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_downstream_path() {
+        let keep = WsProxy::new();
+        assert_eq!(keep.downstream_path("abc", "/ws/abc"), "/ws/abc");
+        assert_eq!(
+            keep.downstream_path("abc", "/ws/abc/v1/realtime"),
+            "/ws/abc/v1/realtime"
+        );
+
+        let strip = WsProxy::new().with_path_mode(WsPathMode::Strip, "");
+        assert_eq!(strip.downstream_path("abc", "/ws/abc"), "/");
+        assert_eq!(
+            strip.downstream_path("abc", "/ws/abc/v1/realtime"),
+            "/v1/realtime"
+        );
+
+        let replace = WsProxy::new().with_path_mode(WsPathMode::Replace, "socket/");
+        assert_eq!(replace.downstream_path("abc", "/ws/abc"), "/socket");
+        assert_eq!(
+            replace.downstream_path("abc", "/ws/abc/v1/realtime"),
+            "/socket/v1/realtime"
+        );
+    }
+
+    #[test]
+    fn test_close_frame_conversion() {
+        let downstream = to_downstream_close(CloseFrame {
+            code: 4001,
+            reason: "app error".into(),
+        });
+        assert_eq!(downstream.code, CloseCode::Library(4001));
+        assert_eq!(downstream.reason.as_str(), "app error");
+
+        let client = to_client_close(TungsteniteCloseFrame {
+            code: CloseCode::Policy,
+            reason: "policy".into(),
+        });
+        assert_eq!(client.code, 1008);
+        assert_eq!(client.reason.as_str(), "policy");
+    }
+
+    #[test]
+    fn test_reserve_connection() {
+        let proxy = WsProxy::new();
+        let first = proxy.reserve_connection("a", Some(2)).unwrap();
+        let _second = proxy.reserve_connection("a", Some(2)).unwrap();
+        assert!(proxy.reserve_connection("a", Some(2)).is_none());
+        assert!(proxy.reserve_connection("b", Some(2)).is_some());
+
+        drop(first);
+        let _third = proxy.reserve_connection("a", Some(2)).unwrap();
+        assert!(proxy.reserve_connection("a", None).is_some());
+        assert!(proxy.reserve_connection("c", Some(0)).is_none());
+        assert!(!proxy.connections.lock().unwrap().contains_key("c"));
+    }
+
+    #[test]
+    fn test_offered_protocols() {
+        let mut headers = HeaderMap::new();
+        assert!(WsProxy::offered_protocols(&headers).is_empty());
+
+        headers.append(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            "graphql-transport-ws, graphql-ws".parse().unwrap(),
+        );
+        headers.append(header::SEC_WEBSOCKET_PROTOCOL, "chat".parse().unwrap());
+        assert_eq!(
+            WsProxy::offered_protocols(&headers),
+            ["graphql-transport-ws", "graphql-ws", "chat"]
+        );
+    }
 }
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::info;
+
+/// Coordinates graceful shutdown between the server and long-lived connections
+#[derive(Clone)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    /// Set to `true` once shutdown has been requested
+    triggered: watch::Sender<bool>,
+    /// Number of tracked connections still running
+    active: watch::Sender<usize>,
+}
+
+impl Shutdown {
+    /// Create a new shutdown coordinator
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                triggered: watch::Sender::new(false),
+                active: watch::Sender::new(0),
+            }),
+        }
+    }
+
+    /// Request shutdown, waking everything waiting on `triggered`
+    pub fn trigger(&self) {
+        self.inner.triggered.send_replace(true);
+    }
+
+    /// Check whether shutdown has been requested
+    pub fn is_triggered(&self) -> bool {
+        *self.inner.triggered.borrow()
+    }
+
+    /// Wait until shutdown has been requested
+    pub async fn triggered(&self) {
+        let mut rx = self.inner.triggered.subscribe();
+        // The sender lives as long as `self`, so this cannot fail
+        let _ = rx.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Track a connection until the returned guard is dropped
+    pub fn track(&self) -> ConnectionGuard {
+        self.inner.active.send_modify(|n| *n += 1);
+        ConnectionGuard {
+            shutdown: self.clone(),
+        }
+    }
+
+    /// Number of tracked connections still running
+    pub fn active(&self) -> usize {
+        *self.inner.active.borrow()
+    }
+
+    /// Wait until all tracked connections have finished
+    pub async fn drained(&self) {
+        let mut rx = self.inner.active.subscribe();
+        let _ = rx.wait_for(|n| *n == 0).await;
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Keeps a connection counted as active while alive
+pub struct ConnectionGuard {
+    shutdown: Shutdown,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.shutdown.inner.active.send_modify(|n| *n -= 1);
+    }
+}
+
+/// Wait for SIGINT (Ctrl+C) or SIGTERM
+pub async fn wait_for_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received SIGINT"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_trigger_and_drain() {
+        let shutdown = Shutdown::new();
+        assert!(!shutdown.is_triggered());
+
+        let guard = shutdown.track();
+        assert_eq!(shutdown.active(), 1);
+
+        let waiter = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.triggered().await }
+        });
+        shutdown.trigger();
+        waiter.await.unwrap();
+        assert!(shutdown.is_triggered());
+
+        let drained = tokio::time::timeout(Duration::from_millis(50), shutdown.drained()).await;
+        assert!(drained.is_err(), "should not drain while a guard is alive");
+
+        drop(guard);
+        tokio::time::timeout(Duration::from_secs(1), shutdown.drained())
+            .await
+            .expect("should drain once all guards are dropped");
+    }
+}
@@ -0,0 +1,227 @@
+use axum::serve::Listener;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject};
+use std::{
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinSet,
+};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{self, ServerConfig},
+    server::TlsStream,
+};
+use tracing::{debug, error, info};
+
+/// Maximum time a client may take to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("Failed to read certificates from {0}: {1}")]
+    Certificate(String, rustls_pki_types::pem::Error),
+
+    #[error("No certificates found in {0}")]
+    NoCertificates(String),
+
+    #[error("Failed to read private key from {0}: {1}")]
+    PrivateKey(String, rustls_pki_types::pem::Error),
+
+    #[error("Invalid TLS configuration: {0}")]
+    InvalidConfig(#[from] rustls::Error),
+}
+
+/// Reloadable rustls server configuration loaded from PEM files
+#[derive(Clone)]
+pub struct TlsConfig {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    current: Arc<RwLock<Arc<ServerConfig>>>,
+}
+
+impl TlsConfig {
+    /// Load the certificate chain and private key from PEM files
+    pub fn load(
+        cert_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+    ) -> Result<Self, TlsError> {
+        let cert_path = cert_path.into();
+        let key_path = key_path.into();
+        let config = build_server_config(&cert_path, &key_path)?;
+
+        Ok(Self {
+            cert_path,
+            key_path,
+            current: Arc::new(RwLock::new(Arc::new(config))),
+        })
+    }
+
+    /// Re-read the PEM files, keeping the current configuration if they are invalid
+    ///
+    /// Only new connections pick up the reloaded certificate.
+    pub fn reload(&self) -> Result<(), TlsError> {
+        let config = build_server_config(&self.cert_path, &self.key_path)?;
+        *self.current.write().unwrap() = Arc::new(config);
+        Ok(())
+    }
+
+    /// Acceptor for the currently loaded configuration
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.current.read().unwrap().clone())
+    }
+}
+
+/// Build a rustls server configuration from a certificate chain and private key
+fn build_server_config(cert_path: &Path, key_path: &Path) -> Result<ServerConfig, TlsError> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| TlsError::Certificate(cert_path.display().to_string(), e))?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(cert_path.display().to_string()));
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| TlsError::PrivateKey(key_path.display().to_string(), e))?;
+
+    let mut config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(config)
+}
+
+/// TCP listener that terminates TLS before handing connections to axum
+///
+/// Handshakes run in the background so a slow client cannot stall `accept`.
+pub struct TlsListener {
+    tcp: TcpListener,
+    tls: TlsConfig,
+    handshakes: JoinSet<Option<(TlsStream<TcpStream>, SocketAddr)>>,
+}
+
+impl TlsListener {
+    /// Wrap a bound TCP listener
+    pub fn new(tcp: TcpListener, tls: TlsConfig) -> Self {
+        Self {
+            tcp,
+            tls,
+            handshakes: JoinSet::new(),
+        }
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            tokio::select! {
+                (stream, addr) = Listener::accept(&mut self.tcp) => {
+                    let acceptor = self.tls.acceptor();
+                    self.handshakes.spawn(handshake(acceptor, stream, addr));
+                }
+                Some(result) = self.handshakes.join_next() => {
+                    if let Ok(Some(conn)) = result {
+                        return conn;
+                    }
+                }
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        self.tcp.local_addr()
+    }
+}
+
+/// Complete the TLS handshake, dropping clients that fail or stall
+async fn handshake(
+    acceptor: TlsAcceptor,
+    stream: TcpStream,
+    addr: SocketAddr,
+) -> Option<(TlsStream<TcpStream>, SocketAddr)> {
+    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+        Ok(Ok(stream)) => Some((stream, addr)),
+        Ok(Err(e)) => {
+            debug!("TLS handshake with {} failed: {}", addr, e);
+            None
+        }
+        Err(_) => {
+            debug!("TLS handshake with {} timed out", addr);
+            None
+        }
+    }
+}
+
+/// Reload certificates from disk whenever the process receives SIGHUP
+#[cfg(unix)]
+pub async fn reload_on_sighup(tls: TlsConfig) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            error!("Failed to install SIGHUP handler: {}", e);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        match tls.reload() {
+            Ok(()) => info!(
+                "🔐 Reloaded TLS certificate from {}",
+                tls.cert_path.display()
+            ),
+            Err(e) => error!(
+                "Failed to reload TLS certificate, keeping the current one: {}",
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/tls")
+            .join(name)
+    }
+
+    #[test]
+    fn test_load_and_reload() {
+        let tls = TlsConfig::load(fixture("cert.pem"), fixture("key.pem")).unwrap();
+        let before = tls.current.read().unwrap().clone();
+
+        tls.reload().unwrap();
+        assert!(!Arc::ptr_eq(&before, &tls.current.read().unwrap()));
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        assert!(matches!(
+            TlsConfig::load(fixture("missing.pem"), fixture("key.pem")),
+            Err(TlsError::Certificate(..))
+        ));
+        assert!(matches!(
+            TlsConfig::load(fixture("key.pem"), fixture("key.pem")),
+            Err(TlsError::NoCertificates(_))
+        ));
+        assert!(matches!(
+            TlsConfig::load(fixture("cert.pem"), fixture("cert.pem")),
+            Err(TlsError::PrivateKey(..))
+        ));
+    }
+}
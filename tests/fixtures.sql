@@ -2,6 +2,7 @@
 -- This file contains test data for HTTP and WebSocket proxy tests
 
 -- Clean up existing test data (if any)
+DELETE FROM session_upstreams WHERE session_id LIKE 'test-%';
 DELETE FROM sessions WHERE session_id LIKE 'test-%';
 
 -- HTTP test sessions (using local Docker services)
@@ -22,3 +23,11 @@ VALUES
 INSERT INTO sessions (session_id, downstream_server_url, downstream_server_status)
 VALUES
   ('test-inactive', 'http://localhost:8888', 'inactive');
+
+-- Session whose only upstream replica is down
+INSERT INTO sessions (session_id, downstream_server_url, downstream_server_status)
+VALUES
+  ('test-upstreams-down', 'http://localhost:8888', 'active');
+INSERT INTO session_upstreams (session_id, upstream_url, weight, status)
+VALUES
+  ('test-upstreams-down', 'http://localhost:8888', 1, 'inactive');
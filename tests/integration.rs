@@ -19,13 +19,20 @@ struct TestServer {
     process: Child,
     port: u16,
     db_path: String,
+    tls: bool,
 }
 
 impl TestServer {
     /// Start a new test server on a unique port
     async fn start() -> Self {
+        Self::start_with_args(&[]).await
+    }
+
+    /// Start a new test server on a unique port with extra CLI arguments
+    async fn start_with_args(extra_args: &[&str]) -> Self {
         let port = allocate_test_port();
         let db_path = format!("./test_sessions_{}.db", port);
+        let tls = extra_args.contains(&"--tls-cert");
 
         println!(
             "🚀 Starting isolated test server on port {} with database {}",
@@ -50,7 +57,7 @@ impl TestServer {
         BUILD_ONCE.call_once(|| {
             println!("🔨 Building project...");
             let build_status = Command::new("cargo")
-                .args(&["build", "--release"])
+                .args(["build", "--release"])
                 .status()
                 .expect("Failed to build project");
             assert!(build_status.success(), "Build failed");
@@ -60,7 +67,7 @@ impl TestServer {
         // Start the server in background with isolated database
         let mut server_cmd = Command::new("./target/release/ss-proxy");
         server_cmd
-            .args(&[
+            .args([
                 "--port",
                 &port.to_string(),
                 "--db-path",
@@ -68,6 +75,7 @@ impl TestServer {
                 "--log-level",
                 "debug",
             ])
+            .args(extra_args)
             .env("TEST_PORT", port.to_string());
 
         // On Unix, set process group ID to enable killing the entire group
@@ -77,6 +85,7 @@ impl TestServer {
             server_cmd.process_group(0);
         }
 
+        #[allow(clippy::zombie_processes)] // reaped in `Drop`
         let process = server_cmd.spawn().expect("Failed to start server");
         let pid = process.id();
 
@@ -85,25 +94,27 @@ impl TestServer {
         // Wait for server to be ready (with health check)
         let client = reqwest::Client::builder()
             .timeout(Duration::from_secs(1))
+            .danger_accept_invalid_certs(tls)
             .build()
             .unwrap();
+        let scheme = if tls { "https" } else { "http" };
 
         for i in 0..30 {
             tokio::time::sleep(Duration::from_millis(200)).await;
 
             if let Ok(response) = client
-                .get(format!("http://localhost:{}/health", port))
+                .get(format!("{}://localhost:{}/health", scheme, port))
                 .send()
                 .await
+                && response.status().is_success()
             {
-                if response.status().is_success() {
-                    println!("✅ Server ready on port {} (took {}ms)", port, i * 200);
-                    return TestServer {
-                        process,
-                        port,
-                        db_path,
-                    };
-                }
+                println!("✅ Server ready on port {} (took {}ms)", port, i * 200);
+                return TestServer {
+                    process,
+                    port,
+                    db_path,
+                    tls,
+                };
             }
         }
 
@@ -112,12 +123,14 @@ impl TestServer {
 
     /// Get the base URL for HTTP tests
     fn base_url(&self) -> String {
-        format!("http://localhost:{}", self.port)
+        let scheme = if self.tls { "https" } else { "http" };
+        format!("{}://localhost:{}", scheme, self.port)
     }
 
     /// Get the WebSocket base URL
     fn ws_base_url(&self) -> String {
-        format!("ws://localhost:{}", self.port)
+        let scheme = if self.tls { "wss" } else { "ws" };
+        format!("{}://localhost:{}", scheme, self.port)
     }
 }
 
@@ -134,7 +147,7 @@ impl Drop for TestServer {
         {
             use std::process::Command as SysCommand;
             let _ = SysCommand::new("kill")
-                .args(&["-TERM", &format!("-{}", pid)])
+                .args(["-TERM", &format!("-{}", pid)])
                 .status();
             std::thread::sleep(std::time::Duration::from_millis(300));
         }
@@ -193,6 +206,37 @@ async fn test_http_proxy_get() {
     assert_eq!(json["url"], "http://localhost:8888/get");
 }
 
+#[tokio::test]
+async fn test_https_termination() {
+    let server = TestServer::start_with_args(&[
+        "--tls-cert",
+        "tests/tls/cert.pem",
+        "--tls-key",
+        "tests/tls/key.pem",
+    ])
+    .await;
+
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let response = client
+        .get(format!("{}/health", server.base_url()))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "OK");
+
+    // Plain HTTP is not served on a TLS listener
+    let plain = reqwest::Client::new()
+        .get(format!("http://localhost:{}/health", server.port))
+        .send()
+        .await;
+    assert!(plain.is_err() || !plain.unwrap().status().is_success());
+}
+
 #[tokio::test]
 async fn test_http_proxy_post() {
     let server = TestServer::start().await;
@@ -260,6 +304,28 @@ async fn test_http_proxy_custom_headers() {
     assert_eq!(json["headers"]["X-Custom-Header"], "test-value");
 }
 
+#[tokio::test]
+async fn test_http_proxy_body_too_large() {
+    let server = TestServer::start_with_args(&["--max-body-size", "1024"]).await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("{}/test-http/post", server.base_url()))
+        .body(vec![b'x'; 512])
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 200);
+
+    let response = client
+        .post(format!("{}/test-http/post", server.base_url()))
+        .body(vec![b'x'; 4096])
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 413);
+}
+
 #[tokio::test]
 async fn test_session_not_found() {
     let server = TestServer::start().await;
@@ -274,6 +340,46 @@ async fn test_session_not_found() {
     assert_eq!(response.status(), 404);
 }
 
+#[tokio::test]
+async fn test_all_upstreams_unavailable() {
+    let server = TestServer::start().await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/test-upstreams-down/get", server.base_url()))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 503);
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint() {
+    let server = TestServer::start().await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/nonexistent-session/get", server.base_url()))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 404);
+
+    let response = client
+        .get(format!("{}/metrics", server.base_url()))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 200);
+
+    let body = response.text().await.expect("Failed to read response");
+    assert!(body.contains(
+        r#"ss_proxy_http_requests_total{session_status="unknown",session_id="",status_class="4xx"} 1"#
+    ));
+    assert!(body.contains("# TYPE ss_proxy_websocket_connections_active gauge"));
+}
+
 #[tokio::test]
 async fn test_inactive_session() {
     let server = TestServer::start().await;
@@ -366,6 +472,72 @@ async fn test_websocket_binary_message() {
     }
 }
 
+#[tokio::test]
+async fn test_websocket_sub_path() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::connect_async;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let server = TestServer::start_with_args(&["--ws-path-mode", "strip"]).await;
+
+    let (ws_stream, _) = connect_async(format!(
+        "{}/ws/test-ws/v1/realtime?model=test",
+        server.ws_base_url()
+    ))
+    .await
+    .expect("Failed to connect to WebSocket sub-path");
+
+    let (mut write, mut read) = ws_stream.split();
+
+    write
+        .send(Message::Text("sub-path".into()))
+        .await
+        .expect("Failed to send message");
+
+    let received = tokio::time::timeout(Duration::from_secs(5), read.next())
+        .await
+        .expect("Timeout waiting for message")
+        .expect("No message received")
+        .expect("Error receiving message");
+
+    assert_eq!(received, Message::Text("sub-path".into()));
+}
+
+#[tokio::test]
+async fn test_websocket_close_code_relayed() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::connect_async;
+    use tokio_tungstenite::tungstenite::Message;
+    use tokio_tungstenite::tungstenite::protocol::{CloseFrame, frame::coding::CloseCode};
+
+    let server = TestServer::start().await;
+
+    let (ws_stream, _) = connect_async(format!("{}/ws/test-ws", server.ws_base_url()))
+        .await
+        .expect("Failed to connect to WebSocket");
+
+    let (mut write, mut read) = ws_stream.split();
+
+    write
+        .send(Message::Close(Some(CloseFrame {
+            code: CloseCode::Library(4000),
+            reason: "done".into(),
+        })))
+        .await
+        .expect("Failed to send close frame");
+
+    let received = tokio::time::timeout(Duration::from_secs(5), read.next())
+        .await
+        .expect("Timeout waiting for close frame")
+        .expect("No message received")
+        .expect("Error receiving message");
+
+    match received {
+        Message::Close(Some(frame)) => assert_eq!(frame.code, CloseCode::Library(4000)),
+        _ => panic!("Expected close frame, got: {:?}", received),
+    }
+}
+
 #[tokio::test]
 async fn test_websocket_session_not_found() {
     use tokio_tungstenite::connect_async;
@@ -420,3 +592,117 @@ async fn test_websocket_multiple_messages() {
         }
     }
 }
+
+// =============================================================================
+// Admin API Tests
+// =============================================================================
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+
+#[tokio::test]
+async fn test_admin_requires_token() {
+    let server = TestServer::start_with_args(&["--admin-token", ADMIN_TOKEN]).await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/admin/sessions", server.base_url()))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 401);
+
+    let response = client
+        .get(format!("{}/admin/sessions", server.base_url()))
+        .bearer_auth("wrong-token")
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn test_admin_session_crud() {
+    let server = TestServer::start_with_args(&["--admin-token", ADMIN_TOKEN]).await;
+    let client = reqwest::Client::new();
+    let sessions_url = format!("{}/admin/sessions", server.base_url());
+
+    // Create
+    let response = client
+        .post(&sessions_url)
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({
+            "session_id": "test-admin",
+            "downstream_server_url": "http://localhost:8888",
+            "downstream_server_status": "active"
+        }))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 201);
+
+    // Duplicate create conflicts
+    let response = client
+        .post(&sessions_url)
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({
+            "session_id": "test-admin",
+            "downstream_server_url": "http://localhost:8888",
+            "downstream_server_status": "active"
+        }))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 409);
+
+    // Newly created session is immediately routable
+    let response = client
+        .get(format!("{}/test-admin/get", server.base_url()))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 200);
+
+    // List with status filter
+    let json: serde_json::Value = client
+        .get(format!("{}?status=inactive", sessions_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .expect("Failed to send request")
+        .json()
+        .await
+        .expect("Failed to parse JSON");
+    assert_eq!(json["total"], 1);
+    assert_eq!(json["sessions"][0]["session_id"], "test-inactive");
+
+    // Patch status
+    let json: serde_json::Value = client
+        .patch(format!("{}/test-admin", sessions_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({ "downstream_server_status": "inactive" }))
+        .send()
+        .await
+        .expect("Failed to send request")
+        .json()
+        .await
+        .expect("Failed to parse JSON");
+    assert_eq!(json["downstream_server_status"], "inactive");
+    assert_eq!(json["downstream_server_url"], "http://localhost:8888");
+
+    // Delete
+    let response = client
+        .delete(format!("{}/test-admin", sessions_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 204);
+
+    let response = client
+        .get(format!("{}/test-admin", sessions_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(response.status(), 404);
+}